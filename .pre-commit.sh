@@ -36,3 +36,4 @@ cargo rustdoc --all-features -- -D warnings
 cargo test --all-features --all-targets
 cargo test --doc
 cargo clippy --all-features --all-targets -- -D warnings
+cargo clippy --no-default-features --features serde --all-targets -- -D warnings
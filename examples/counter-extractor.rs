@@ -1,37 +1,27 @@
-//! The example illustrates accessing cookies from an
-//! [`axum_core::extract::FromRequest::from_request`] implementation.
-//! The behavior is the same as `examples/counter.rs` but cookies leveraging is moved into an
-//! extractor.
+//! The example illustrates extracting a single parsed cookie value with
+//! [`tower_cookies::extract::OptionalCookie`].
+//! The behavior is the same as `examples/counter.rs` but the cookie is declared as a type.
 use axum::{routing::get, Router};
-use axum_core::extract::FromRequestParts;
-use http::request::Parts;
-use std::net::SocketAddr;
-use tower_cookies::{Cookie, CookieManagerLayer, Cookies};
+use std::{net::SocketAddr, num::ParseIntError, str::FromStr};
+use tower_cookies::{
+    extract::{CookieValue, OptionalCookie},
+    Cookie, CookieManagerLayer, Cookies,
+};
 
-const COOKIE_NAME: &str = "visited";
+struct Visited(usize);
 
-struct Counter(usize);
+impl FromStr for Visited {
+    type Err = ParseIntError;
 
-impl<S> FromRequestParts<S> for Counter
-where
-    S: Send + Sync,
-{
-    type Rejection = (http::StatusCode, &'static str);
-
-    async fn from_request_parts(req: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
-        let cookies = Cookies::from_request_parts(req, state).await?;
-
-        let visited = cookies
-            .get(COOKIE_NAME)
-            .and_then(|c| c.value().parse().ok())
-            .unwrap_or(0)
-            + 1;
-        cookies.add(Cookie::new(COOKIE_NAME, visited.to_string()));
-
-        Ok(Counter(visited))
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.parse().map(Self)
     }
 }
 
+impl CookieValue for Visited {
+    const NAME: &'static str = "visited";
+}
+
 #[tokio::main]
 async fn main() {
     let app = Router::new()
@@ -45,6 +35,8 @@ async fn main() {
         .unwrap();
 }
 
-async fn handler(counter: Counter) -> String {
-    format!("You have visited this page {} times", counter.0)
+async fn handler(OptionalCookie(visited): OptionalCookie<Visited>, cookies: Cookies) -> String {
+    let visited = visited.map_or(0, |v| v.0) + 1;
+    cookies.add(Cookie::new(Visited::NAME, visited.to_string()));
+    format!("You have visited this page {} times", visited)
 }
@@ -2,20 +2,17 @@
 //! Can be run by: `cargo run --all-features --example signed_private`
 use axum::{routing::get, Router};
 use std::net::SocketAddr;
-use std::sync::OnceLock;
 use tower_cookies::{Cookie, CookieManagerLayer, Cookies, Key};
 
 const COOKIE_NAME: &str = "visited_private";
-static KEY: OnceLock<Key> = OnceLock::new();
 
 #[tokio::main]
 async fn main() {
     let my_key: &[u8] = &[0; 64]; // Your real key must be cryptographically random
-    KEY.set(Key::from(my_key)).ok();
 
     let app = Router::new()
         .route("/", get(handler))
-        .layer(CookieManagerLayer::new());
+        .layer(CookieManagerLayer::new().with_key(Key::from(my_key)));
 
     let addr = SocketAddr::from(([127, 0, 0, 1], 3000));
     let listener = tokio::net::TcpListener::bind(&addr).await.unwrap();
@@ -25,8 +22,8 @@ async fn main() {
 }
 
 async fn handler(cookies: Cookies) -> String {
-    let key = KEY.get().unwrap();
-    let private_cookies = cookies.private(key); // You can use `cookies.signed` as well
+    // You can use `cookies.signed_owned` as well
+    let private_cookies = cookies.private_owned().unwrap();
 
     let visited = private_cookies
         .get(COOKIE_NAME)
@@ -0,0 +1,155 @@
+use crate::Cookies;
+use cookie::Cookie;
+
+/// Cookie changes returned from a handler as a part of the response.
+///
+/// [`CookieManager`](crate::CookieManager) applies the changes to the same jar as the ones made
+/// through [`Cookies`], after the handler returns, so the changes of both kinds are sent in a
+/// single set of `Set-Cookie` headers. If a cookie is changed in both ways, the returned change
+/// wins. Without [`CookieManagerLayer`](crate::CookieManagerLayer), the changes are ignored.
+///
+/// # Example:
+/// ```
+/// use axum::{http::header, response::IntoResponse, routing::post, Router};
+/// use tower_cookies::{Cookie, CookieChanges, CookieManagerLayer};
+///
+/// async fn logout() -> impl IntoResponse {
+///     (
+///         CookieChanges::new().remove(Cookie::new("session", "")),
+///         [(header::LOCATION, "/")],
+///     )
+/// }
+///
+/// let app: Router = Router::new()
+///     .route("/logout", post(logout))
+///     .layer(CookieManagerLayer::new());
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct CookieChanges {
+    changes: Vec<Change>,
+}
+
+#[derive(Clone, Debug)]
+enum Change {
+    Add(Cookie<'static>),
+    Remove(Cookie<'static>),
+}
+
+impl CookieChanges {
+    /// Creates an empty set of changes.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the `cookie`, like [`Cookies::add`].
+    #[allow(clippy::should_implement_trait)]
+    pub fn add(mut self, cookie: Cookie<'static>) -> Self {
+        self.changes.push(Change::Add(cookie));
+        self
+    }
+
+    /// Removes the `cookie`, like [`Cookies::remove`].
+    pub fn remove(mut self, cookie: Cookie<'static>) -> Self {
+        self.changes.push(Change::Remove(cookie));
+        self
+    }
+
+    /// Returns `true` if there are no changes.
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    /// Applies the changes in the order they were made.
+    pub(crate) fn apply(self, cookies: &Cookies) {
+        for change in self.changes {
+            match change {
+                Change::Add(cookie) => cookies.add(cookie),
+                Change::Remove(cookie) => cookies.remove(cookie),
+            }
+        }
+    }
+}
+
+#[cfg(feature = "axum-core")]
+impl axum_core::response::IntoResponseParts for CookieChanges {
+    type Error = std::convert::Infallible;
+
+    fn into_response_parts(
+        self,
+        mut res: axum_core::response::ResponseParts,
+    ) -> Result<axum_core::response::ResponseParts, Self::Error> {
+        // Several changes returned in a tuple are merged
+        match res.extensions_mut().get_mut::<CookieChanges>() {
+            Some(existing) => existing.changes.extend(self.changes),
+            None => {
+                res.extensions_mut().insert(self);
+            }
+        }
+        Ok(res)
+    }
+}
+
+#[cfg(feature = "axum-core")]
+impl axum_core::response::IntoResponse for CookieChanges {
+    fn into_response(self) -> axum_core::response::Response {
+        (self, ()).into_response()
+    }
+}
+
+#[cfg(all(test, feature = "axum-core"))]
+mod tests {
+    use super::*;
+    use crate::CookieManagerLayer;
+    use axum::{body::Body, routing::get, Router};
+    use http::{header, Request};
+    use tower::ServiceExt;
+
+    #[tokio::test]
+    async fn merged_with_jar() {
+        let app = Router::new()
+            .route(
+                "/",
+                get(|cookies: Cookies| async move {
+                    cookies.add(Cookie::new("a", "1"));
+                    cookies.add(Cookie::new("b", "1"));
+                    (
+                        CookieChanges::new().add(Cookie::new("a", "2")),
+                        CookieChanges::new().remove(Cookie::new("c", "")),
+                        "body",
+                    )
+                }),
+            )
+            .layer(CookieManagerLayer::new());
+        let req = Request::builder()
+            .uri("/")
+            .header(header::COOKIE, "c=1")
+            .body(Body::empty())
+            .unwrap();
+        let res = app.oneshot(req).await.unwrap();
+        assert!(res.extensions().get::<CookieChanges>().is_none());
+        let mut hdrs: Vec<_> = res
+            .headers()
+            .get_all(header::SET_COOKIE)
+            .iter()
+            .map(|v| v.to_str().unwrap().to_owned())
+            .collect();
+        hdrs.sort();
+        assert_eq!(hdrs.len(), 3);
+        assert_eq!(hdrs[0], "a=2");
+        assert_eq!(hdrs[1], "b=1");
+        assert!(hdrs[2].starts_with("c=; Max-Age=0"));
+    }
+
+    #[tokio::test]
+    async fn without_cookies_extractor() {
+        let app = Router::new()
+            .route(
+                "/",
+                get(|| async { CookieChanges::new().add(Cookie::new("a", "1")) }),
+            )
+            .layer(CookieManagerLayer::new());
+        let req = Request::builder().uri("/").body(Body::empty()).unwrap();
+        let res = app.oneshot(req).await.unwrap();
+        assert_eq!(res.headers()[header::SET_COOKIE], "a=1");
+    }
+}
@@ -0,0 +1,180 @@
+//! Splitting of oversized cookies into `name.0`, `name.1`, ... chunks.
+
+use cookie::{time::Duration, Cookie};
+use std::collections::HashMap;
+
+/// Joins chunked cookies into the original ones.
+///
+/// Returns the cookies and the number of chunks of every cookie, which is zero for cookies which
+/// weren't chunked.
+pub(crate) fn join(
+    cookies: Vec<Cookie<'static>>,
+) -> (Vec<Cookie<'static>>, HashMap<String, usize>) {
+    let mut chunks: HashMap<String, Vec<(usize, String)>> = HashMap::new();
+    let mut plain = Vec::new();
+    for cookie in cookies {
+        match split_name(cookie.name()) {
+            Some((name, index)) => chunks
+                .entry(name.to_owned())
+                .or_default()
+                .push((index, cookie.value().to_owned())),
+            None => plain.push(cookie),
+        }
+    }
+
+    let mut counts: HashMap<String, usize> =
+        plain.iter().map(|c| (c.name().to_owned(), 0)).collect();
+    let mut joined = Vec::new();
+    for (name, mut parts) in chunks {
+        parts.sort_by_key(|(index, _)| *index);
+        let complete = parts.iter().enumerate().all(|(i, (index, _))| i == *index);
+        if complete {
+            counts.insert(name.clone(), parts.len());
+            let value: String = parts.into_iter().map(|(_, value)| value).collect();
+            joined.push(Cookie::new(name, value));
+        } else {
+            // Not a chunked cookie, or some chunks are lost, so keep the parts as they are
+            joined.extend(
+                parts
+                    .into_iter()
+                    .map(|(index, value)| Cookie::new(format!("{name}.{index}"), value)),
+            );
+        }
+    }
+    // Chunked cookies replace plain cookies with the same name
+    plain.retain(|c| !joined.iter().any(|j| j.name() == c.name()));
+    plain.extend(joined);
+    (plain, counts)
+}
+
+/// Splits the outgoing `cookie` into chunks with values not longer than `max_size` bytes.
+///
+/// `original_chunks` is the number of chunks the client has, as returned by [`join`], the stale
+/// ones are removed.
+pub(crate) fn split(
+    cookie: Cookie<'static>,
+    max_size: usize,
+    original_chunks: Option<usize>,
+) -> Vec<Cookie<'static>> {
+    let had_plain = original_chunks == Some(0);
+    let original_chunks = original_chunks.unwrap_or_default();
+    let is_removal = cookie.max_age() == Some(Duration::ZERO);
+    let mut cookies = Vec::new();
+
+    let new_chunks = if is_removal || cookie.value().len() <= max_size {
+        0
+    } else {
+        let value = cookie.value().to_owned();
+        let mut rest = value.as_str();
+        while !rest.is_empty() {
+            let mut end = max_size.min(rest.len());
+            while !rest.is_char_boundary(end) {
+                end -= 1;
+            }
+            if end == 0 {
+                end = rest.chars().next().map_or(1, char::len_utf8);
+            }
+            let mut chunk = cookie.clone();
+            chunk.set_name(format!("{}.{}", cookie.name(), cookies.len()));
+            chunk.set_value(rest[..end].to_owned());
+            cookies.push(chunk);
+            rest = &rest[end..];
+        }
+        cookies.len()
+    };
+
+    for index in new_chunks..original_chunks {
+        let mut removal = cookie.clone();
+        removal.set_name(format!("{}.{index}", cookie.name()));
+        removal.set_value("");
+        removal.make_removal();
+        cookies.push(removal);
+    }
+    if new_chunks == 0 {
+        if !is_removal || original_chunks == 0 {
+            cookies.push(cookie);
+        }
+    } else if had_plain {
+        let mut removal = cookie;
+        removal.set_value("");
+        removal.make_removal();
+        cookies.push(removal);
+    }
+    cookies
+}
+
+fn split_name(name: &str) -> Option<(&str, usize)> {
+    let (name, index) = name.rsplit_once('.')?;
+    if name.is_empty() || !index.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    Some((name, index.parse().ok()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(cookies: &[Cookie<'_>]) -> Vec<String> {
+        cookies
+            .iter()
+            .map(|c| {
+                let removed = c.max_age() == Some(Duration::ZERO);
+                format!("{}{}", if removed { "-" } else { "" }, c.name())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn join_chunks() {
+        let (cookies, counts) = join(vec![
+            Cookie::new("a.1", "cd"),
+            Cookie::new("b", "1"),
+            Cookie::new("a.0", "ab"),
+            Cookie::new("c.1", "x"),
+        ]);
+        let mut cookies: Vec<_> = cookies.iter().map(|c| c.to_string()).collect();
+        cookies.sort();
+        assert_eq!(cookies, ["a=abcd", "b=1", "c.1=x"]);
+        assert_eq!(counts["a"], 2);
+        assert_eq!(counts["b"], 0);
+    }
+
+    #[test]
+    fn split_large() {
+        let cookies = split(Cookie::new("a", "abcde"), 2, None);
+        let values: Vec<_> = cookies.iter().map(|c| c.to_string()).collect();
+        assert_eq!(values[..3], ["a.0=ab", "a.1=cd", "a.2=e"]);
+        assert_eq!(names(&cookies), ["a.0", "a.1", "a.2"]);
+
+        let cookies = split(Cookie::new("a", "abcde"), 2, Some(0));
+        assert_eq!(names(&cookies), ["a.0", "a.1", "a.2", "-a"]);
+
+        let cookies = split(Cookie::new("a", "abc"), 2, Some(3));
+        assert_eq!(names(&cookies), ["a.0", "a.1", "-a.2"]);
+    }
+
+    #[test]
+    fn split_small() {
+        let cookies = split(Cookie::new("a", "ab"), 2, None);
+        assert_eq!(names(&cookies), ["a"]);
+
+        let cookies = split(Cookie::new("a", "ab"), 2, Some(2));
+        assert_eq!(names(&cookies), ["-a.0", "-a.1", "a"]);
+    }
+
+    #[test]
+    fn split_removal() {
+        let mut cookie = Cookie::new("a", "");
+        cookie.make_removal();
+        assert_eq!(names(&split(cookie.clone(), 2, Some(0))), ["-a"]);
+        assert_eq!(names(&split(cookie, 2, Some(2))), ["-a.0", "-a.1"]);
+    }
+
+    #[test]
+    fn split_utf8() {
+        let cookies = split(Cookie::new("a", "ééé"), 3, None);
+        let values: Vec<_> = cookies.iter().map(|c| c.value().to_owned()).collect();
+        assert_eq!(values, ["é", "é", "é"]);
+    }
+}
@@ -0,0 +1,38 @@
+//! [`Future`] types.
+
+use super::{jar::Target, ClientJar};
+use futures_util::ready;
+use http::Response;
+use pin_project_lite::pin_project;
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+pin_project! {
+    /// Response future for [`ClientCookies`](super::ClientCookies).
+    #[derive(Debug)]
+    pub struct ResponseFuture<F> {
+        #[pin]
+        pub(crate) future: F,
+        pub(crate) jar: ClientJar,
+        pub(crate) target: Option<Target>,
+    }
+}
+
+impl<F, ResBody, E> Future for ResponseFuture<F>
+where
+    F: Future<Output = Result<Response<ResBody>, E>>,
+{
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let res = ready!(this.future.poll(cx)?);
+        if let Some(target) = this.target {
+            this.jar.insert_headers_for(target, res.headers());
+        }
+        Poll::Ready(Ok(res))
+    }
+}
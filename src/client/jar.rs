@@ -0,0 +1,322 @@
+#[cfg(feature = "psl")]
+use crate::psl::is_public_suffix;
+use cookie::{
+    time::{Duration, OffsetDateTime},
+    Cookie, CookieJar,
+};
+use http::{header, HeaderMap, HeaderValue, Request, Uri};
+use parking_lot::Mutex;
+use std::{collections::BTreeMap, net::IpAddr, sync::Arc};
+
+/// A browser-like cookie jar of an HTTP client.
+///
+/// The jar stores cookies received in `Set-Cookie` headers, respecting their `Domain`, `Path`,
+/// `Secure`, `Expires` and `Max-Age` attributes, and returns the ones matching the URLs of the
+/// outgoing requests. Cookies without the `Domain` attribute are host-only, i.e. they're only
+/// sent to the host which set them.
+///
+/// The jar is cheap to clone, all the clones refer to the same cookies.
+///
+/// # Example:
+/// ```
+/// use tower_cookies::{client::ClientJar, Cookie};
+///
+/// let jar = ClientJar::new();
+/// let uri = "https://example.com/account/login".parse().unwrap();
+/// jar.insert(&uri, Cookie::parse("id=1; Path=/account").unwrap());
+///
+/// let uri = "https://example.com/account/settings".parse().unwrap();
+/// assert_eq!(jar.cookie_header(&uri).unwrap(), "id=1");
+/// let uri = "https://example.com/".parse().unwrap();
+/// assert_eq!(jar.cookie_header(&uri), None);
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct ClientJar {
+    store: Arc<Mutex<Store>>,
+}
+
+/// Cookies grouped by their domain and path, so a name is unique inside a [`CookieJar`].
+///
+/// The stored cookies always have the `Path` attribute, the `Domain` attribute is only kept for
+/// cookies which aren't host-only. `Max-Age` is converted to `Expires` when the cookie is stored.
+type Store = BTreeMap<(String, String), CookieJar>;
+
+/// The parts of a request URL relevant to cookies.
+#[derive(Clone, Debug)]
+pub(crate) struct Target {
+    host: String,
+    path: String,
+    secure: bool,
+}
+
+impl Target {
+    /// Returns the target of an absolute `uri`.
+    pub(crate) fn from_uri(uri: &Uri) -> Option<Self> {
+        let host = uri.host()?;
+        Some(Self::new(host, uri))
+    }
+
+    /// Returns the target of a `request`, taking the host from the `Host` header if the URI is
+    /// relative.
+    pub(crate) fn from_request<B>(request: &Request<B>) -> Option<Self> {
+        if let Some(target) = Self::from_uri(request.uri()) {
+            return Some(target);
+        }
+        let host = request.headers().get(header::HOST)?.to_str().ok()?;
+        let host = match host.rsplit_once(':') {
+            Some((host, port)) if port.bytes().all(|b| b.is_ascii_digit()) => host,
+            _ => host,
+        };
+        Some(Self::new(host, request.uri()))
+    }
+
+    fn new(host: &str, uri: &Uri) -> Self {
+        let host = host.trim_start_matches('[').trim_end_matches(']');
+        Self {
+            host: host.trim_end_matches('.').to_ascii_lowercase(),
+            path: uri.path().to_owned(),
+            secure: uri.scheme_str() == Some("https"),
+        }
+    }
+}
+
+impl ClientJar {
+    /// Creates an empty jar.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Stores the `cookie` received in a response to the request to `uri`.
+    ///
+    /// Returns `false` if the cookie was rejected, e.g. because its domain doesn't match the
+    /// host of the `uri` or the `uri` is relative. An expired cookie removes the stored one.
+    pub fn insert(&self, uri: &Uri, cookie: Cookie<'static>) -> bool {
+        match Target::from_uri(uri) {
+            Some(target) => self.insert_for(&target, cookie),
+            None => false,
+        }
+    }
+
+    /// Stores the cookies of the `Set-Cookie` headers of a response to the request to `uri`.
+    pub fn insert_from_headers(&self, uri: &Uri, headers: &HeaderMap) {
+        if let Some(target) = Target::from_uri(uri) {
+            self.insert_headers_for(&target, headers);
+        }
+    }
+
+    /// Returns the unexpired cookies which should be sent with a request to `uri`, with longer
+    /// paths first.
+    pub fn matching(&self, uri: &Uri) -> Vec<Cookie<'static>> {
+        match Target::from_uri(uri) {
+            Some(target) => self.matching_for(&target),
+            None => Vec::new(),
+        }
+    }
+
+    /// Returns the value of the `Cookie` header for a request to `uri`. Returns [`None`] if there
+    /// are no matching cookies.
+    pub fn cookie_header(&self, uri: &Uri) -> Option<HeaderValue> {
+        header_value(&self.matching(uri))
+    }
+
+    /// Returns all the stored cookies, including the expired ones which haven't been removed
+    /// yet.
+    ///
+    /// Every cookie has the `Path` attribute set, and the cookies which aren't host-only have the
+    /// `Domain` attribute set.
+    pub fn list(&self) -> Vec<Cookie<'static>> {
+        self.store
+            .lock()
+            .values()
+            .flat_map(|jar| jar.iter().cloned())
+            .collect()
+    }
+
+    /// Removes the expired cookies.
+    pub fn remove_expired(&self) {
+        let now = OffsetDateTime::now_utc();
+        let mut store = self.store.lock();
+        for jar in store.values_mut() {
+            let expired: Vec<_> = jar
+                .iter()
+                .filter(|c| is_expired(c, now))
+                .map(|c| c.name().to_owned())
+                .collect();
+            for name in expired {
+                jar.force_remove(name);
+            }
+        }
+        store.retain(|_, jar| jar.iter().next().is_some());
+    }
+
+    /// Removes all the cookies.
+    pub fn clear(&self) {
+        self.store.lock().clear();
+    }
+
+    /// Returns the stored cookies with the domains they're stored under.
+    pub(super) fn entries(&self) -> Vec<(String, Cookie<'static>)> {
+        self.store
+            .lock()
+            .iter()
+            .flat_map(|((domain, _), jar)| jar.iter().map(|c| (domain.clone(), c.clone())))
+            .collect()
+    }
+
+    /// Stores the `cookie` under the `domain` without checking it against a request, unless
+    /// the cookie is expired or is a domain cookie for a public suffix. The cookie is host-only
+    /// if it has no `Domain` attribute.
+    pub(super) fn insert_entry(&self, domain: String, mut cookie: Cookie<'static>) {
+        if is_expired(&cookie, OffsetDateTime::now_utc()) {
+            return;
+        }
+        // Like in `insert_for`, a cookie for a public suffix can only be host-only
+        if cookie.domain().is_some() && is_public_suffix(&domain) {
+            return;
+        }
+        let path = cookie.path().unwrap_or("/").to_owned();
+        cookie.set_path(path.clone());
+        let mut store = self.store.lock();
+        store
+            .entry((domain, path))
+            .or_default()
+            .add_original(cookie);
+    }
+
+    pub(crate) fn insert_headers_for(&self, target: &Target, headers: &HeaderMap) {
+        for header in headers.get_all(header::SET_COOKIE) {
+            let Ok(value) = header.to_str() else {
+                continue;
+            };
+            if let Ok(cookie) = Cookie::parse(value.to_owned()) {
+                self.insert_for(target, cookie);
+            }
+        }
+    }
+
+    pub(crate) fn insert_for(&self, target: &Target, mut cookie: Cookie<'static>) -> bool {
+        let domain = match cookie.domain() {
+            Some(domain) => {
+                let domain = domain.trim_end_matches('.').to_ascii_lowercase();
+                if !domain_match(&target.host, &domain) {
+                    return false;
+                }
+                let public = is_public_suffix(&domain);
+                if public && domain != target.host {
+                    return false;
+                }
+                // A cookie for an IP address or a public suffix can only be host-only
+                if public || (domain == target.host && target.host.parse::<IpAddr>().is_ok()) {
+                    cookie.unset_domain();
+                } else {
+                    cookie.set_domain(domain.clone());
+                }
+                domain
+            }
+            None => target.host.clone(),
+        };
+        if cookie.secure() == Some(true) && !target.secure {
+            return false;
+        }
+        let path = match cookie.path() {
+            Some(path) if path.starts_with('/') => path.to_owned(),
+            _ => default_path(&target.path).to_owned(),
+        };
+        cookie.set_path(path.clone());
+
+        // Like browsers, limit the lifetime to 400 days
+        let now = OffsetDateTime::now_utc();
+        let max_expires = now + Duration::days(400);
+        if let Some(max_age) = cookie.max_age() {
+            cookie.set_max_age(None);
+            let max_age = max_age.clamp(Duration::ZERO, Duration::days(400));
+            cookie.set_expires(now + max_age);
+        } else if let Some(expires) = cookie.expires_datetime() {
+            cookie.set_expires(expires.min(max_expires));
+        }
+
+        let mut store = self.store.lock();
+        let key = (domain, path);
+        if is_expired(&cookie, now) {
+            if let Some(jar) = store.get_mut(&key) {
+                jar.force_remove(cookie.name());
+                if jar.iter().next().is_none() {
+                    store.remove(&key);
+                }
+            }
+        } else {
+            store.entry(key).or_default().add_original(cookie);
+        }
+        true
+    }
+
+    pub(crate) fn matching_for(&self, target: &Target) -> Vec<Cookie<'static>> {
+        let now = OffsetDateTime::now_utc();
+        let store = self.store.lock();
+        let mut cookies: Vec<_> = store
+            .iter()
+            .filter(|((domain, path), _)| {
+                domain_match(&target.host, domain) && path_match(&target.path, path)
+            })
+            .flat_map(|((domain, _), jar)| {
+                // Host-only cookies are only sent to the exact host which set them
+                jar.iter()
+                    .filter(move |c| c.domain().is_some() || *domain == target.host)
+            })
+            .filter(|c| (c.secure() != Some(true) || target.secure) && !is_expired(c, now))
+            .cloned()
+            .collect();
+        cookies.sort_by_key(|c| std::cmp::Reverse(c.path().map_or(0, str::len)));
+        cookies
+    }
+}
+
+/// Returns the `Cookie` header value containing the `cookies`.
+pub(crate) fn header_value(cookies: &[Cookie<'_>]) -> Option<HeaderValue> {
+    if cookies.is_empty() {
+        return None;
+    }
+    let value = cookies
+        .iter()
+        .map(|c| format!("{}={}", c.name(), c.value()))
+        .collect::<Vec<_>>()
+        .join("; ");
+    HeaderValue::from_str(&value).ok()
+}
+
+pub(crate) fn is_expired(cookie: &Cookie<'_>, now: OffsetDateTime) -> bool {
+    cookie
+        .expires_datetime()
+        .is_some_and(|expires| expires <= now)
+}
+
+#[cfg(not(feature = "psl"))]
+fn is_public_suffix(_domain: &str) -> bool {
+    false
+}
+
+/// Whether the `host` domain-matches the `domain` (RFC 6265, section 5.1.3).
+fn domain_match(host: &str, domain: &str) -> bool {
+    if host == domain {
+        return true;
+    }
+    host.strip_suffix(domain)
+        .is_some_and(|rest| rest.ends_with('.'))
+        && host.parse::<IpAddr>().is_err()
+}
+
+/// Whether the request `path` path-matches the cookie `path` (RFC 6265, section 5.1.4).
+fn path_match(request_path: &str, path: &str) -> bool {
+    request_path == path
+        || request_path
+            .strip_prefix(path)
+            .is_some_and(|rest| path.ends_with('/') || rest.starts_with('/'))
+}
+
+/// The default `Path` of cookies set by a request to `path` (RFC 6265, section 5.1.4).
+fn default_path(path: &str) -> &str {
+    match path.rfind('/') {
+        Some(0) | None => "/",
+        Some(index) => &path[..index],
+    }
+}
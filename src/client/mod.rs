@@ -0,0 +1,259 @@
+//! Cookie handling for HTTP clients.
+//!
+//! [`ClientCookieLayer`] wraps a tower-based HTTP client, storing the cookies of the responses in
+//! a [`ClientJar`] and attaching the matching ones to the outgoing requests, like a browser does.
+//!
+//! ```
+//! use http::{header, Request, Response};
+//! use std::convert::Infallible;
+//! use tower::{service_fn, ServiceBuilder, ServiceExt};
+//! use tower_cookies::client::ClientCookieLayer;
+//!
+//! # tokio::runtime::Runtime::new().unwrap().block_on(async {
+//! // A stand-in for an HTTP client, e.g. `hyper_util::client::legacy::Client`
+//! let client = service_fn(|req: Request<()>| async move {
+//!     let cookie = req.headers().get(header::COOKIE).cloned();
+//!     let res = Response::builder().header(header::SET_COOKIE, "id=1");
+//!     Ok::<_, Infallible>(res.body(cookie).unwrap())
+//! });
+//! let layer = ClientCookieLayer::new();
+//! let jar = layer.jar().clone();
+//! let client = ServiceBuilder::new().layer(layer).service(client);
+//!
+//! let req = || Request::get("https://example.com/").body(()).unwrap();
+//! assert_eq!(client.clone().oneshot(req()).await.unwrap().into_body(), None);
+//! assert_eq!(client.oneshot(req()).await.unwrap().into_body().unwrap(), "id=1");
+//! assert_eq!(jar.list().len(), 1);
+//! # });
+//! ```
+
+use self::future::ResponseFuture;
+use self::jar::{header_value, Target};
+use http::{header, HeaderValue, Request, Response};
+use std::task::{Context, Poll};
+use tower_layer::Layer;
+use tower_service::Service;
+
+pub use self::jar::ClientJar;
+
+pub mod future;
+mod jar;
+mod persist;
+
+/// Layer to apply [`ClientCookies`] middleware.
+#[derive(Clone, Debug, Default)]
+pub struct ClientCookieLayer {
+    jar: ClientJar,
+}
+
+impl ClientCookieLayer {
+    /// Creates a layer with an empty jar.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a layer using the `jar`, e.g. shared with other clients or loaded from a file.
+    pub fn with_jar(jar: ClientJar) -> Self {
+        Self { jar }
+    }
+
+    /// Returns the jar of the layer.
+    pub fn jar(&self) -> &ClientJar {
+        &self.jar
+    }
+}
+
+impl<S> Layer<S> for ClientCookieLayer {
+    type Service = ClientCookies<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        ClientCookies {
+            inner,
+            jar: self.jar.clone(),
+        }
+    }
+}
+
+/// Middleware storing the cookies of responses and sending them back with requests.
+#[derive(Clone, Debug)]
+pub struct ClientCookies<S> {
+    inner: S,
+    jar: ClientJar,
+}
+
+impl<S> ClientCookies<S> {
+    /// Creates the middleware using the `jar`.
+    pub fn new(inner: S, jar: ClientJar) -> Self {
+        Self { inner, jar }
+    }
+
+    /// Returns the jar of the middleware.
+    pub fn jar(&self) -> &ClientJar {
+        &self.jar
+    }
+}
+
+impl<ReqBody, ResBody, S> Service<Request<ReqBody>> for ClientCookies<S>
+where
+    S: Service<Request<ReqBody>, Response = Response<ResBody>>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = ResponseFuture<S::Future>;
+
+    #[inline]
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut req: Request<ReqBody>) -> Self::Future {
+        let target = Target::from_request(&req);
+        if let Some(target) = &target {
+            let cookies = self.jar.matching_for(target);
+            if let Some(value) = header_value(&cookies) {
+                // Cookies set explicitly by the caller go first
+                let value = match req.headers_mut().remove(header::COOKIE) {
+                    Some(existing) => {
+                        let mut merged = existing.as_bytes().to_vec();
+                        merged.extend_from_slice(b"; ");
+                        merged.extend_from_slice(value.as_bytes());
+                        HeaderValue::from_bytes(&merged).unwrap_or(value)
+                    }
+                    None => value,
+                };
+                req.headers_mut().insert(header::COOKIE, value);
+            }
+        }
+        ResponseFuture {
+            future: self.inner.call(req),
+            jar: self.jar.clone(),
+            target,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cookie::Cookie;
+    use http::Uri;
+    use std::convert::Infallible;
+    use tower::{service_fn, ServiceExt};
+
+    /// Responds with the `Cookie` header of the request, setting the cookies of the `set` query.
+    async fn echo(req: Request<()>) -> Result<Response<String>, Infallible> {
+        let mut res = Response::builder();
+        if let Some(query) = req.uri().query() {
+            for cookie in query.split('&') {
+                let cookie = cookie.replace("%20", " ").replace("%3B", ";");
+                res = res.header(header::SET_COOKIE, cookie);
+            }
+        }
+        let cookie = req.headers().get(header::COOKIE);
+        let body = cookie.map(|v| v.to_str().unwrap().to_owned());
+        Ok(res.body(body.unwrap_or_default()).unwrap())
+    }
+
+    async fn get<S>(client: &S, uri: &str) -> String
+    where
+        S: Service<Request<()>, Response = Response<String>, Error = Infallible> + Clone,
+    {
+        let req = Request::get(uri).body(()).unwrap();
+        client.clone().oneshot(req).await.unwrap().into_body()
+    }
+
+    #[tokio::test]
+    async fn round_trip() {
+        let client = ClientCookieLayer::new().layer(service_fn(echo));
+        get(&client, "http://a.com/?x=1&y=2%3B%20Path=/y").await;
+        assert_eq!(get(&client, "http://a.com/").await, "x=1");
+        assert_eq!(get(&client, "http://a.com/y/z").await, "y=2; x=1");
+        assert_eq!(get(&client, "http://b.com/").await, "");
+
+        get(&client, "http://a.com/?x=;%20Max-Age=0").await;
+        assert_eq!(get(&client, "http://a.com/").await, "");
+    }
+
+    #[tokio::test]
+    async fn explicit_cookie_header() {
+        let client = ClientCookieLayer::new().layer(service_fn(echo));
+        get(&client, "http://a.com/?x=1").await;
+        let req = Request::get("http://a.com/")
+            .header(header::COOKIE, "y=2")
+            .body(())
+            .unwrap();
+        let res = client.clone().oneshot(req).await.unwrap();
+        assert_eq!(res.into_body(), "y=2; x=1");
+    }
+
+    #[test]
+    fn domains() {
+        let jar = ClientJar::new();
+        let uri: Uri = "http://www.a.com/".parse().unwrap();
+        assert!(jar.insert(&uri, Cookie::parse("host=1").unwrap()));
+        assert!(jar.insert(&uri, Cookie::parse("domain=1; Domain=a.com").unwrap()));
+        assert!(!jar.insert(&uri, Cookie::parse("other=1; Domain=b.com").unwrap()));
+        assert!(!jar.insert(&uri, Cookie::parse("sub=1; Domain=x.www.a.com").unwrap()));
+
+        let header = |uri: &str| {
+            let value = jar.cookie_header(&uri.parse().unwrap());
+            value.map(|v| v.to_str().unwrap().to_owned())
+        };
+        assert_eq!(header("http://www.a.com/").unwrap(), "domain=1; host=1");
+        assert_eq!(header("http://x.www.a.com/").unwrap(), "domain=1");
+        assert_eq!(header("http://a.com/").unwrap(), "domain=1");
+        assert_eq!(header("http://xa.com/"), None);
+    }
+
+    #[test]
+    fn secure() {
+        let jar = ClientJar::new();
+        let http: Uri = "http://a.com/".parse().unwrap();
+        let https: Uri = "https://a.com/".parse().unwrap();
+        assert!(!jar.insert(&http, Cookie::parse("x=1; Secure").unwrap()));
+        assert!(jar.insert(&https, Cookie::parse("x=1; Secure").unwrap()));
+        assert_eq!(jar.cookie_header(&http), None);
+        assert_eq!(jar.cookie_header(&https).unwrap(), "x=1");
+    }
+
+    #[test]
+    fn paths() {
+        let jar = ClientJar::new();
+        let uri: Uri = "http://a.com/docs/page".parse().unwrap();
+        jar.insert(&uri, Cookie::parse("x=1").unwrap());
+        let header = |uri: &str| jar.cookie_header(&uri.parse().unwrap());
+        assert!(header("http://a.com/docs").is_some());
+        assert!(header("http://a.com/docs/other").is_some());
+        assert!(header("http://a.com/docsother").is_none());
+        assert!(header("http://a.com/").is_none());
+    }
+
+    #[test]
+    fn expiry() {
+        let jar = ClientJar::new();
+        let uri: Uri = "http://a.com/".parse().unwrap();
+        jar.insert(&uri, Cookie::parse("x=1; Max-Age=60").unwrap());
+        jar.insert(
+            &uri,
+            Cookie::parse("y=1; Expires=Wed, 21 Oct 2015 07:28:00 GMT").unwrap(),
+        );
+        assert_eq!(jar.cookie_header(&uri).unwrap(), "x=1");
+        let cookie = &jar.list()[0];
+        assert_eq!(cookie.max_age(), None);
+        assert!(cookie.expires_datetime().is_some());
+    }
+
+    #[cfg(feature = "psl")]
+    #[test]
+    fn public_suffix() {
+        let jar = ClientJar::new();
+        let uri: Uri = "http://www.a.co.uk/".parse().unwrap();
+        assert!(!jar.insert(&uri, Cookie::parse("x=1; Domain=co.uk").unwrap()));
+        assert!(jar.insert(&uri, Cookie::parse("y=1; Domain=a.co.uk").unwrap()));
+
+        let uri: Uri = "http://github.io/".parse().unwrap();
+        assert!(jar.insert(&uri, Cookie::parse("z=1; Domain=github.io").unwrap()));
+        let uri: Uri = "http://user.github.io/".parse().unwrap();
+        assert_eq!(jar.cookie_header(&uri), None);
+    }
+}
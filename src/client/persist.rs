@@ -0,0 +1,302 @@
+//! Saving and loading of [`ClientJar`].
+
+use super::ClientJar;
+use cookie::{time::OffsetDateTime, Cookie};
+use std::io::{self, BufRead, Write};
+
+const NETSCAPE_HEADER: &str = "# Netscape HTTP Cookie File";
+const HTTP_ONLY_PREFIX: &str = "#HttpOnly_";
+
+impl ClientJar {
+    /// Loads a jar from the Netscape `cookies.txt` format used by curl and wget.
+    ///
+    /// Expired cookies, malformed lines and, with the `psl` feature, cookies for public suffixes,
+    /// e.g. `co.uk`, are skipped. Cookies with zero expiration time are loaded as session cookies.
+    ///
+    /// # Example:
+    /// ```
+    /// use tower_cookies::client::ClientJar;
+    ///
+    /// let file = "example.com\tFALSE\t/\tTRUE\t0\tid\t1\n";
+    /// let jar = ClientJar::load_netscape(file.as_bytes()).unwrap();
+    /// let uri = "https://example.com/".parse().unwrap();
+    /// assert_eq!(jar.cookie_header(&uri).unwrap(), "id=1");
+    /// ```
+    pub fn load_netscape(reader: impl BufRead) -> io::Result<Self> {
+        let jar = Self::new();
+        for line in reader.lines() {
+            if let Some((domain, cookie)) = parse_netscape_line(&line?) {
+                jar.insert_entry(domain, cookie);
+            }
+        }
+        Ok(jar)
+    }
+
+    /// Saves the unexpired cookies in the Netscape `cookies.txt` format used by curl and wget.
+    ///
+    /// # Example:
+    /// ```
+    /// use std::fs::File;
+    /// use tower_cookies::client::ClientJar;
+    ///
+    /// # let dir = std::env::temp_dir();
+    /// # let path = dir.join("tower-cookies-doc-cookies.txt");
+    /// let jar = ClientJar::new();
+    /// jar.save_netscape(File::create(&path)?)?;
+    /// let jar = ClientJar::load_netscape(std::io::BufReader::new(File::open(&path)?))?;
+    /// # std::fs::remove_file(&path)?;
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn save_netscape(&self, mut writer: impl Write) -> io::Result<()> {
+        writeln!(writer, "{NETSCAPE_HEADER}")?;
+        let now = OffsetDateTime::now_utc();
+        for (domain, cookie) in self.entries() {
+            let Some(expires) = expires_timestamp(&cookie, now) else {
+                continue;
+            };
+            let http_only = if cookie.http_only() == Some(true) {
+                HTTP_ONLY_PREFIX
+            } else {
+                ""
+            };
+            let (domain, subdomains) = match cookie.domain() {
+                Some(_) => (format!(".{domain}"), "TRUE"),
+                None => (domain, "FALSE"),
+            };
+            writeln!(
+                writer,
+                "{http_only}{domain}\t{subdomains}\t{}\t{}\t{expires}\t{}\t{}",
+                cookie.path().unwrap_or("/"),
+                bool_field(cookie.secure() == Some(true)),
+                cookie.name(),
+                cookie.value(),
+            )?;
+        }
+        writer.flush()
+    }
+
+    /// Loads a jar from the JSON format written by [`ClientJar::save_json`].
+    ///
+    /// Expired cookies and, with the `psl` feature, cookies for public suffixes are skipped.
+    #[cfg(feature = "serde")]
+    pub fn load_json(reader: impl io::Read) -> io::Result<Self> {
+        let entries: Vec<serde_json::Value> = serde_json::from_reader(reader)?;
+        let jar = Self::new();
+        for entry in entries {
+            let (domain, cookie) = json::parse(&entry)
+                .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "malformed cookie"))?;
+            jar.insert_entry(domain, cookie);
+        }
+        Ok(jar)
+    }
+
+    /// Saves the unexpired cookies as a JSON array of objects with `name`, `value`, `domain`,
+    /// `host_only`, `path`, `secure`, `http_only`, `same_site` and `expires` (a Unix timestamp,
+    /// or `null` for session cookies) fields.
+    #[cfg(feature = "serde")]
+    pub fn save_json(&self, mut writer: impl Write) -> io::Result<()> {
+        let now = OffsetDateTime::now_utc();
+        let entries: Vec<_> = self
+            .entries()
+            .into_iter()
+            .filter(|(_, cookie)| expires_timestamp(cookie, now).is_some())
+            .map(|(domain, cookie)| json::format(domain, &cookie))
+            .collect();
+        serde_json::to_writer_pretty(&mut writer, &entries)?;
+        writer.flush()
+    }
+}
+
+#[cfg(feature = "serde")]
+mod json {
+    use cookie::{time::OffsetDateTime, Cookie, SameSite};
+    use serde_json::{json, Value};
+
+    pub(super) fn format(domain: String, cookie: &Cookie<'_>) -> Value {
+        json!({
+            "name": cookie.name(),
+            "value": cookie.value(),
+            "domain": domain,
+            "host_only": cookie.domain().is_none(),
+            "path": cookie.path().unwrap_or("/"),
+            "secure": cookie.secure() == Some(true),
+            "http_only": cookie.http_only() == Some(true),
+            "same_site": cookie.same_site().map(|s| s.to_string()),
+            "expires": cookie.expires_datetime().map(OffsetDateTime::unix_timestamp),
+        })
+    }
+
+    pub(super) fn parse(entry: &Value) -> Option<(String, Cookie<'static>)> {
+        let field = |name| entry.get(name).and_then(Value::as_str);
+        let flag = |name| entry.get(name).and_then(Value::as_bool).unwrap_or_default();
+        let domain = field("domain")?.to_ascii_lowercase();
+        let mut cookie = Cookie::build((field("name")?.to_owned(), field("value")?.to_owned()))
+            .path(field("path").unwrap_or("/").to_owned())
+            .secure(flag("secure"))
+            .http_only(flag("http_only"))
+            .build();
+        if !flag("host_only") {
+            cookie.set_domain(domain.clone());
+        }
+        cookie.set_same_site(match field("same_site") {
+            Some("Strict") => Some(SameSite::Strict),
+            Some("Lax") => Some(SameSite::Lax),
+            Some("None") => Some(SameSite::None),
+            _ => None,
+        });
+        match entry.get("expires") {
+            None | Some(Value::Null) => (),
+            Some(value) => {
+                let timestamp = value.as_i64()?;
+                cookie.set_expires(OffsetDateTime::from_unix_timestamp(timestamp).ok()?);
+            }
+        }
+        Some((domain, cookie))
+    }
+}
+
+fn parse_netscape_line(line: &str) -> Option<(String, Cookie<'static>)> {
+    let (line, http_only) = match line.strip_prefix(HTTP_ONLY_PREFIX) {
+        Some(line) => (line, true),
+        None => (line, false),
+    };
+    if line.starts_with('#') || line.trim().is_empty() {
+        return None;
+    }
+    let fields: Vec<_> = line.trim_end_matches(['\r', '\n']).split('\t').collect();
+    let [domain, subdomains, path, secure, expires, name, value] = fields[..] else {
+        return None;
+    };
+    let host_only = !parse_bool_field(subdomains)?;
+    let domain = domain.trim_start_matches('.').to_ascii_lowercase();
+    let mut cookie = Cookie::build((name.to_owned(), value.to_owned()))
+        .path(path.to_owned())
+        .secure(parse_bool_field(secure)?)
+        .http_only(http_only)
+        .build();
+    if !host_only {
+        cookie.set_domain(domain.clone());
+    }
+    match expires.parse::<i64>().ok()? {
+        0 => (),
+        timestamp => cookie.set_expires(OffsetDateTime::from_unix_timestamp(timestamp).ok()?),
+    }
+    Some((domain, cookie))
+}
+
+/// Returns the expiration time for the Netscape format, which is zero for session cookies, or
+/// [`None`] if the cookie is expired.
+fn expires_timestamp(cookie: &Cookie<'_>, now: OffsetDateTime) -> Option<i64> {
+    match cookie.expires_datetime() {
+        Some(expires) if expires <= now => None,
+        Some(expires) => Some(expires.unix_timestamp()),
+        None => Some(0),
+    }
+}
+
+fn bool_field(value: bool) -> &'static str {
+    if value {
+        "TRUE"
+    } else {
+        "FALSE"
+    }
+}
+
+fn parse_bool_field(value: &str) -> Option<bool> {
+    match value {
+        "TRUE" => Some(true),
+        "FALSE" => Some(false),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use http::Uri;
+
+    fn jar() -> ClientJar {
+        let jar = ClientJar::new();
+        let uri: Uri = "https://www.a.com/".parse().unwrap();
+        for cookie in [
+            "host=1; Secure; HttpOnly",
+            "domain=2; Domain=a.com; Path=/docs; Max-Age=60",
+            "expired=3; Max-Age=0",
+        ] {
+            jar.insert(&uri, Cookie::parse(cookie).unwrap());
+        }
+        jar
+    }
+
+    fn sorted(jar: &ClientJar) -> Vec<String> {
+        let mut cookies: Vec<_> = jar
+            .entries()
+            .into_iter()
+            .map(|(domain, c)| {
+                format!(
+                    "{domain} {}={} {:?} {:?} {} {} {:?}",
+                    c.name(),
+                    c.value(),
+                    c.domain(),
+                    c.path(),
+                    c.secure() == Some(true),
+                    c.http_only() == Some(true),
+                    c.expires_datetime().map(OffsetDateTime::unix_timestamp),
+                )
+            })
+            .collect();
+        cookies.sort();
+        cookies
+    }
+
+    #[test]
+    fn netscape() {
+        let jar = jar();
+        let mut file = Vec::new();
+        jar.save_netscape(&mut file).unwrap();
+        let file = String::from_utf8(file).unwrap();
+        let lines: Vec<_> = file.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert!(lines.contains(&"#HttpOnly_www.a.com\tFALSE\t/\tTRUE\t0\thost\t1"));
+        assert!(lines
+            .iter()
+            .any(|l| l.starts_with(".a.com\tTRUE\t/docs\tFALSE\t")));
+
+        let loaded = ClientJar::load_netscape(file.as_bytes()).unwrap();
+        assert_eq!(sorted(&loaded), sorted(&jar));
+    }
+
+    #[test]
+    fn netscape_pruning() {
+        let file = "# comment\n\
+            a.com\tFALSE\t/\tFALSE\t1\texpired\t1\n\
+            a.com\tFALSE\t/\tFALSE\t4102444800\tfresh\t1\n\
+            malformed\n";
+        let jar = ClientJar::load_netscape(file.as_bytes()).unwrap();
+        let names: Vec<_> = jar.list().iter().map(|c| c.name().to_owned()).collect();
+        assert_eq!(names, ["fresh"]);
+    }
+
+    #[cfg(feature = "psl")]
+    #[test]
+    fn netscape_public_suffix() {
+        let file = ".co.uk\tTRUE\t/\tFALSE\t0\tsuffix\t1\n\
+            localhost\tFALSE\t/\tFALSE\t0\thost\t1\n\
+            .a.co.uk\tTRUE\t/\tFALSE\t0\tdomain\t1\n";
+        let jar = ClientJar::load_netscape(file.as_bytes()).unwrap();
+        let mut names: Vec<_> = jar.list().iter().map(|c| c.name().to_owned()).collect();
+        names.sort();
+        assert_eq!(names, ["domain", "host"]);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn json() {
+        let jar = jar();
+        let mut file = Vec::new();
+        jar.save_json(&mut file).unwrap();
+        let loaded = ClientJar::load_json(file.as_slice()).unwrap();
+        assert_eq!(sorted(&loaded), sorted(&jar));
+        assert!(ClientJar::load_json(&b"[{}]"[..]).is_err());
+    }
+}
@@ -0,0 +1,467 @@
+//! CSRF protection using double-submit cookies.
+//!
+//! [`CsrfLayer`] issues a random token in a cookie signed by the key set by
+//! [`CookieManagerLayer::with_key`](crate::CookieManagerLayer::with_key) and makes it available
+//! to handlers through the [`CsrfToken`] extractor. Requests with unsafe methods (everything
+//! except `GET`, `HEAD`, `OPTIONS` and `TRACE`) are rejected with `403 Forbidden` unless they
+//! submit the same token in a header or in a URL-encoded form field.
+//!
+//! The layer relies on [`Cookies`], so it should be applied inside [`CookieManagerLayer`]:
+//!
+//! ```
+//! use axum::{
+//!     routing::{get, post},
+//!     Router,
+//! };
+//! use tower_cookies::{
+//!     csrf::{CsrfLayer, CsrfToken},
+//!     CookieManagerLayer, Key,
+//! };
+//!
+//! async fn form(token: CsrfToken) -> String {
+//!     format!(r#"<form method="post"><input name="csrf_token" value="{token}"></form>"#)
+//! }
+//!
+//! let app: Router = Router::new()
+//!     .route("/", get(form).post(|| async { "Saved" }))
+//!     .route("/webhook", post(|| async {}))
+//!     .layer(CsrfLayer::new().exempt("/webhook"))
+//!     .layer(CookieManagerLayer::new().with_key(Key::generate()));
+//! ```
+//!
+//! [`CookieManagerLayer`]: crate::CookieManagerLayer
+
+use crate::Cookies;
+use axum_core::{
+    body::Body,
+    response::{IntoResponse, Response},
+};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use cookie::{Cookie, SameSite};
+use http::{header, HeaderName, HeaderValue, Method, Request, StatusCode, Uri};
+use http_body_util::{BodyExt, Limited};
+use std::{
+    borrow::Cow,
+    fmt,
+    future::Future,
+    mem,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+use tower_layer::Layer;
+use tower_service::Service;
+
+/// Layer to apply [`Csrf`] middleware.
+#[derive(Clone, Debug, Default)]
+pub struct CsrfLayer {
+    config: Arc<Config>,
+}
+
+#[derive(Clone, Debug)]
+struct Config {
+    cookie_name: Cow<'static, str>,
+    header_name: HeaderName,
+    form_field: Cow<'static, str>,
+    max_form_size: usize,
+    exempt: Vec<Cow<'static, str>>,
+    exempt_prefixes: Vec<Cow<'static, str>>,
+    check_origin: bool,
+    allowed_origins: Vec<HeaderValue>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            cookie_name: "csrf_token".into(),
+            header_name: HeaderName::from_static("x-csrf-token"),
+            form_field: "csrf_token".into(),
+            max_form_size: 64 * 1024,
+            exempt: Vec::new(),
+            exempt_prefixes: Vec::new(),
+            check_origin: false,
+            allowed_origins: Vec::new(),
+        }
+    }
+}
+
+impl CsrfLayer {
+    /// Creates a CSRF layer with the token in the `csrf_token` cookie, accepting the submitted
+    /// token in the `x-csrf-token` header or the `csrf_token` form field.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the name of the token cookie.
+    pub fn cookie_name(mut self, name: impl Into<Cow<'static, str>>) -> Self {
+        Arc::make_mut(&mut self.config).cookie_name = name.into();
+        self
+    }
+
+    /// Sets the name of the header containing the submitted token.
+    pub fn header_name(mut self, name: HeaderName) -> Self {
+        Arc::make_mut(&mut self.config).header_name = name;
+        self
+    }
+
+    /// Sets the name of the form field containing the submitted token.
+    pub fn form_field(mut self, name: impl Into<Cow<'static, str>>) -> Self {
+        Arc::make_mut(&mut self.config).form_field = name.into();
+        self
+    }
+
+    /// Sets the maximum size of a form body buffered to find the token, 64 KiB by default. Larger
+    /// forms are rejected with `413 Payload Too Large`.
+    pub fn max_form_size(mut self, bytes: usize) -> Self {
+        Arc::make_mut(&mut self.config).max_form_size = bytes;
+        self
+    }
+
+    /// Disables the check for requests to the exact `path`.
+    pub fn exempt(mut self, path: impl Into<Cow<'static, str>>) -> Self {
+        Arc::make_mut(&mut self.config).exempt.push(path.into());
+        self
+    }
+
+    /// Disables the check for requests to paths starting with the `prefix`.
+    pub fn exempt_prefix(mut self, prefix: impl Into<Cow<'static, str>>) -> Self {
+        Arc::make_mut(&mut self.config)
+            .exempt_prefixes
+            .push(prefix.into());
+        self
+    }
+
+    /// Enables the check of `Origin` header, or `Referer` if there's no `Origin`, of the requests
+    /// with unsafe methods. Requests without both headers are rejected.
+    ///
+    /// Without [`CsrfLayer::allowed_origin`], the origin's host should match the `Host` header.
+    pub fn check_origin(mut self, value: bool) -> Self {
+        Arc::make_mut(&mut self.config).check_origin = value;
+        self
+    }
+
+    /// Adds an allowed origin, e.g. `https://example.com`, and enables the origin check.
+    pub fn allowed_origin(mut self, origin: HeaderValue) -> Self {
+        let config = Arc::make_mut(&mut self.config);
+        config.check_origin = true;
+        config.allowed_origins.push(origin);
+        self
+    }
+}
+
+impl<S> Layer<S> for CsrfLayer {
+    type Service = Csrf<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        Csrf {
+            inner,
+            config: self.config.clone(),
+        }
+    }
+}
+
+/// Middleware protecting from CSRF, see the [module](self) docs.
+#[derive(Clone, Debug)]
+pub struct Csrf<S> {
+    inner: S,
+    config: Arc<Config>,
+}
+
+impl<S> Service<Request<Body>> for Csrf<S>
+where
+    S: Service<Request<Body>, Response = Response> + Clone + Send + 'static,
+    S::Future: Send,
+{
+    type Response = Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Response, S::Error>> + Send>>;
+
+    #[inline]
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request<Body>) -> Self::Future {
+        // Take the service which is ready, leaving its clone in place
+        let clone = self.inner.clone();
+        let mut inner = mem::replace(&mut self.inner, clone);
+        let config = self.config.clone();
+        Box::pin(async move {
+            match config.check(req).await {
+                Ok(req) => inner.call(req).await,
+                Err(res) => Ok(res),
+            }
+        })
+    }
+}
+
+impl Config {
+    /// Issues the token and checks the request, returning the rejection response on failure.
+    async fn check(&self, mut req: Request<Body>) -> Result<Request<Body>, Response> {
+        let signed = req
+            .extensions()
+            .get::<Cookies>()
+            .ok_or_else(|| internal_error("Can't find cookies. Is `CookieManagerLayer` enabled?"))?
+            .signed_owned()
+            .ok_or_else(|| internal_error("`CsrfLayer` requires `CookieManagerLayer::with_key`"))?;
+
+        let existing = signed.get(&self.cookie_name).map(|c| c.value().to_owned());
+        let token = match &existing {
+            Some(token) => token.clone(),
+            None => {
+                let token = URL_SAFE_NO_PAD.encode(rand::random::<[u8; 32]>());
+                signed.add(
+                    Cookie::build((self.cookie_name.clone(), token.clone()))
+                        .path("/")
+                        .http_only(true)
+                        .same_site(SameSite::Lax)
+                        .build(),
+                );
+                token
+            }
+        };
+        req.extensions_mut().insert(CsrfToken(token));
+
+        if is_safe(req.method()) || self.is_exempt(req.uri().path()) {
+            return Ok(req);
+        }
+        if self.check_origin && !self.origin_allowed(&req) {
+            return Err(forbidden("Origin not allowed"));
+        }
+        let Some(expected) = existing else {
+            return Err(forbidden("Missing CSRF cookie"));
+        };
+
+        let submitted = match req.headers().get(&self.header_name) {
+            Some(value) => value.to_str().ok().map(str::to_owned),
+            None if is_form(&req) => {
+                let (parts, body) = req.into_parts();
+                let bytes = Limited::new(body, self.max_form_size)
+                    .collect()
+                    .await
+                    .map_err(|_| {
+                        (StatusCode::PAYLOAD_TOO_LARGE, "Can't read the form").into_response()
+                    })?
+                    .to_bytes();
+                let token = form_value(&bytes, &self.form_field);
+                req = Request::from_parts(parts, Body::from(bytes));
+                token
+            }
+            None => None,
+        };
+        match submitted {
+            Some(token) if constant_time_eq(token.as_bytes(), expected.as_bytes()) => Ok(req),
+            _ => Err(forbidden("Invalid CSRF token")),
+        }
+    }
+
+    fn is_exempt(&self, path: &str) -> bool {
+        self.exempt.iter().any(|p| p == path)
+            || self.exempt_prefixes.iter().any(|p| path.starts_with(&**p))
+    }
+
+    fn origin_allowed(&self, req: &Request<Body>) -> bool {
+        let headers = req.headers();
+        let origin = match headers.get(header::ORIGIN) {
+            Some(origin) => origin.to_str().ok().and_then(|o| o.parse::<Uri>().ok()),
+            None => headers
+                .get(header::REFERER)
+                .and_then(|r| r.to_str().ok())
+                .and_then(|r| r.parse::<Uri>().ok()),
+        };
+        let Some(origin) = origin else {
+            return false;
+        };
+        let (Some(scheme), Some(authority)) = (origin.scheme_str(), origin.authority()) else {
+            return false;
+        };
+        if self.allowed_origins.is_empty() {
+            let host = headers
+                .get(header::HOST)
+                .and_then(|h| h.to_str().ok())
+                .or_else(|| req.uri().authority().map(|a| a.as_str()));
+            return host.is_some_and(|h| h.eq_ignore_ascii_case(authority.as_str()));
+        }
+        let origin = format!("{scheme}://{authority}");
+        self.allowed_origins
+            .iter()
+            .any(|o| o.as_bytes().eq_ignore_ascii_case(origin.as_bytes()))
+    }
+}
+
+/// The CSRF token of the current request to be submitted with forms or sent by scripts in the
+/// header.
+///
+/// Available to handlers behind [`CsrfLayer`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CsrfToken(String);
+
+impl CsrfToken {
+    /// Returns the token.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for CsrfToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+fn is_safe(method: &Method) -> bool {
+    matches!(
+        *method,
+        Method::GET | Method::HEAD | Method::OPTIONS | Method::TRACE
+    )
+}
+
+fn is_form(req: &Request<Body>) -> bool {
+    req.headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.starts_with("application/x-www-form-urlencoded"))
+}
+
+/// Finds the value of the `field` in a URL-encoded form.
+fn form_value(form: &[u8], field: &str) -> Option<String> {
+    form.split(|&b| b == b'&').find_map(|pair| {
+        let mut parts = pair.splitn(2, |&b| b == b'=');
+        let name = url_decode(parts.next()?)?;
+        if name != field {
+            return None;
+        }
+        url_decode(parts.next().unwrap_or_default())
+    })
+}
+
+fn url_decode(value: &[u8]) -> Option<String> {
+    let value: Vec<u8> = value
+        .iter()
+        .map(|&b| if b == b'+' { b' ' } else { b })
+        .collect();
+    percent_encoding::percent_decode(&value)
+        .decode_utf8()
+        .ok()
+        .map(Cow::into_owned)
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+fn forbidden(message: &'static str) -> Response {
+    (StatusCode::FORBIDDEN, message).into_response()
+}
+
+fn internal_error(message: &'static str) -> Response {
+    (StatusCode::INTERNAL_SERVER_ERROR, message).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CookieManagerLayer, Key};
+    use axum::{
+        routing::{get, post},
+        Router,
+    };
+    use tower::ServiceExt;
+
+    fn app(csrf: CsrfLayer) -> Router {
+        Router::new()
+            .route(
+                "/",
+                get(|token: CsrfToken| async move { token.to_string() })
+                    .post(|body: String| async move { body }),
+            )
+            .route("/webhook", post(|| async {}))
+            .layer(csrf.exempt("/webhook"))
+            .layer(CookieManagerLayer::new().with_key(Key::from(&[1; 64])))
+    }
+
+    /// Returns the token and the cookie.
+    async fn token(app: &Router) -> (String, String) {
+        let req = Request::get("/").body(Body::empty()).unwrap();
+        let res = app.clone().oneshot(req).await.unwrap();
+        let cookie = res.headers()[header::SET_COOKIE].to_str().unwrap();
+        let cookie = cookie.split(';').next().unwrap().to_owned();
+        let body = res.into_body().collect().await.unwrap().to_bytes();
+        (String::from_utf8(body.to_vec()).unwrap(), cookie)
+    }
+
+    async fn status(app: &Router, req: Request<Body>) -> StatusCode {
+        app.clone().oneshot(req).await.unwrap().status()
+    }
+
+    #[tokio::test]
+    async fn header_token() {
+        let app = app(CsrfLayer::new());
+        let (token, cookie) = token(&app).await;
+
+        let req = Request::post("/").body(Body::empty()).unwrap();
+        assert_eq!(status(&app, req).await, StatusCode::FORBIDDEN);
+
+        let req = Request::post("/")
+            .header(header::COOKIE, &cookie)
+            .header("x-csrf-token", "wrong")
+            .body(Body::empty())
+            .unwrap();
+        assert_eq!(status(&app, req).await, StatusCode::FORBIDDEN);
+
+        let req = Request::post("/")
+            .header(header::COOKIE, &cookie)
+            .header("x-csrf-token", &token)
+            .body(Body::empty())
+            .unwrap();
+        assert_eq!(status(&app, req).await, StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn form_token() {
+        let app = app(CsrfLayer::new());
+        let (token, cookie) = token(&app).await;
+        let form = format!("name=foo+bar&csrf_token={token}");
+        let req = Request::post("/")
+            .header(header::COOKIE, &cookie)
+            .header(header::CONTENT_TYPE, "application/x-www-form-urlencoded")
+            .body(Body::from(form.clone()))
+            .unwrap();
+        let res = app.oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        let body = res.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(body, form);
+    }
+
+    #[tokio::test]
+    async fn exempt() {
+        let app = app(CsrfLayer::new());
+        let req = Request::post("/webhook").body(Body::empty()).unwrap();
+        assert_eq!(status(&app, req).await, StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn origin() {
+        let app = app(CsrfLayer::new().allowed_origin(HeaderValue::from_static("https://a.com")));
+        let (token, cookie) = token(&app).await;
+        for (origin, expected) in [
+            ("https://a.com", StatusCode::OK),
+            ("https://b.com", StatusCode::FORBIDDEN),
+        ] {
+            let req = Request::post("/")
+                .header(header::COOKIE, &cookie)
+                .header(header::ORIGIN, origin)
+                .header("x-csrf-token", &token)
+                .body(Body::empty())
+                .unwrap();
+            assert_eq!(status(&app, req).await, expected);
+        }
+    }
+
+    #[test]
+    fn parse_form() {
+        assert_eq!(form_value(b"a=1&b=x%20y+z", "b").unwrap(), "x y z");
+        assert_eq!(form_value(b"a=1", "b"), None);
+        assert_eq!(form_value(b"b", "b").unwrap(), "");
+    }
+}
@@ -1,6 +1,187 @@
+//! Axum extractors of cookies.
+//!
+//! Besides the whole jar, i.e. [`Cookies`] and the owned [`SignedCookies`](crate::SignedCookies)
+//! and [`PrivateCookies`](crate::PrivateCookies), single cookies can be extracted as values
+//! implementing [`CookieValue`] with [`Cookie`], [`SignedCookie`] and [`OptionalCookie`].
+//!
+//! # Example:
+//! ```
+//! use axum::{routing::get, Router};
+//! use std::{num::ParseIntError, str::FromStr};
+//! use tower_cookies::{
+//!     extract::{Cookie, CookieValue},
+//!     CookieManagerLayer,
+//! };
+//!
+//! struct UserId(u64);
+//!
+//! impl FromStr for UserId {
+//!     type Err = ParseIntError;
+//!
+//!     fn from_str(s: &str) -> Result<Self, Self::Err> {
+//!         s.parse().map(Self)
+//!     }
+//! }
+//!
+//! impl CookieValue for UserId {
+//!     const NAME: &'static str = "user_id";
+//! }
+//!
+//! // Rejected with `400 Bad Request` if the cookie is missing or isn't a number
+//! async fn handler(Cookie(UserId(id)): Cookie<UserId>) -> String {
+//!     format!("user {id}")
+//! }
+//!
+//! let app: Router = Router::new()
+//!     .route("/", get(handler))
+//!     .layer(CookieManagerLayer::new());
+//! ```
+
 use crate::Cookies;
-use axum_core::extract::FromRequestParts;
+#[cfg(any(feature = "signed", feature = "private"))]
+use crate::{key::KeyRef, Key};
+#[cfg(any(feature = "signed", feature = "private"))]
+use axum_core::extract::FromRef;
+use axum_core::{
+    extract::FromRequestParts,
+    response::{IntoResponse, Response},
+};
 use http::{request::Parts, StatusCode};
+use std::{error::Error, fmt, str::FromStr};
+
+/// A value stored in the cookie named [`CookieValue::NAME`], parsed with [`FromStr`].
+pub trait CookieValue: FromStr {
+    /// The name of the cookie.
+    const NAME: &'static str;
+}
+
+/// Extractor of the value of a [`CookieValue`] cookie.
+///
+/// Rejects the request with `400 Bad Request` if the cookie is missing or fails to parse.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Cookie<T>(pub T);
+
+/// Extractor of the value of a [`CookieValue`] cookie, signed with the keys used by the
+/// [`SignedCookies`](crate::SignedCookies) extractor.
+///
+/// Rejects the request with `400 Bad Request` if the cookie is missing or fails to parse, and
+/// with `401 Unauthorized` if it fails to verify.
+#[cfg(feature = "signed")]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SignedCookie<T>(pub T);
+
+/// Extractor of the value of an optional [`CookieValue`] cookie.
+///
+/// Rejects the request with `400 Bad Request` if the cookie fails to parse.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct OptionalCookie<T>(pub Option<T>);
+
+/// Rejection of the cookie extractors.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum CookieRejection {
+    /// [`CookieManagerLayer`](crate::CookieManagerLayer) isn't enabled.
+    MissingLayer,
+    /// The cookie with the given name is missing.
+    Missing(&'static str),
+    /// The value of the cookie with the given name fails to parse.
+    Invalid(&'static str),
+    /// The signed cookie with the given name fails to verify.
+    Tampered(&'static str),
+}
+
+impl CookieRejection {
+    /// Returns the status code of the rejection response.
+    pub fn status(&self) -> StatusCode {
+        match self {
+            Self::MissingLayer => StatusCode::INTERNAL_SERVER_ERROR,
+            Self::Missing(_) | Self::Invalid(_) => StatusCode::BAD_REQUEST,
+            Self::Tampered(_) => StatusCode::UNAUTHORIZED,
+        }
+    }
+}
+
+impl fmt::Display for CookieRejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingLayer => {
+                f.write_str("Can't extract cookies. Is `CookieManagerLayer` enabled?")
+            }
+            Self::Missing(name) => write!(f, "Missing cookie `{name}`"),
+            Self::Invalid(name) => write!(f, "Invalid cookie `{name}`"),
+            Self::Tampered(name) => write!(f, "Cookie `{name}` fails to verify"),
+        }
+    }
+}
+
+impl Error for CookieRejection {}
+
+impl IntoResponse for CookieRejection {
+    fn into_response(self) -> Response {
+        (self.status(), self.to_string()).into_response()
+    }
+}
+
+fn cookies(parts: &Parts) -> Result<&Cookies, CookieRejection> {
+    parts
+        .extensions
+        .get::<Cookies>()
+        .ok_or(CookieRejection::MissingLayer)
+}
+
+fn parse<T: CookieValue>(value: &str) -> Result<T, CookieRejection> {
+    value.parse().map_err(|_| CookieRejection::Invalid(T::NAME))
+}
+
+impl<S, T> FromRequestParts<S> for Cookie<T>
+where
+    S: Sync + Send,
+    T: CookieValue,
+{
+    type Rejection = CookieRejection;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let cookie = cookies(parts)?
+            .get(T::NAME)
+            .ok_or(CookieRejection::Missing(T::NAME))?;
+        parse(cookie.value()).map(Self)
+    }
+}
+
+#[cfg(feature = "signed")]
+impl<S, T> FromRequestParts<S> for SignedCookie<T>
+where
+    S: Sync + Send,
+    Key: FromRef<S>,
+    T: CookieValue,
+{
+    type Rejection = CookieRejection;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let cookies = cookies(parts)?;
+        let signed = signed(cookies, state);
+        match signed.get(T::NAME) {
+            Some(cookie) => parse(cookie.value()).map(Self),
+            None if cookies.get(T::NAME).is_some() => Err(CookieRejection::Tampered(T::NAME)),
+            None => Err(CookieRejection::Missing(T::NAME)),
+        }
+    }
+}
+
+impl<S, T> FromRequestParts<S> for OptionalCookie<T>
+where
+    S: Sync + Send,
+    T: CookieValue,
+{
+    type Rejection = CookieRejection;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        match cookies(parts)?.get(T::NAME) {
+            Some(cookie) => parse(cookie.value()).map(|value| Self(Some(value))),
+            None => Ok(Self(None)),
+        }
+    }
+}
 
 impl<S> FromRequestParts<S> for Cookies
 where
@@ -15,3 +196,270 @@ where
         ))
     }
 }
+
+/// Extracts [`SignedCookies`](crate::SignedCookies) using the keys set by
+/// [`CookieManagerLayer::with_key`](crate::CookieManagerLayer::with_key) or
+/// [`CookieManagerLayer::with_key_ring`](crate::CookieManagerLayer::with_key_ring), or the
+/// [`Key`] of the router state if the layer has no keys.
+///
+/// # Example:
+/// ```
+/// use axum::{extract::FromRef, routing::get, Router};
+/// use tower_cookies::{Cookie, CookieManagerLayer, Key, SignedCookies};
+///
+/// #[derive(Clone)]
+/// struct AppState {
+///     key: Key,
+/// }
+///
+/// impl FromRef<AppState> for Key {
+///     fn from_ref(state: &AppState) -> Self {
+///         state.key.clone()
+///     }
+/// }
+///
+/// async fn handler(cookies: SignedCookies<'static>) {
+///     cookies.add(Cookie::new("user", "alice"));
+/// }
+///
+/// let state = AppState {
+///     key: Key::generate(),
+/// };
+/// let app: Router = Router::new()
+///     .route("/", get(handler))
+///     .layer(CookieManagerLayer::new())
+///     .with_state(state);
+/// ```
+#[cfg(feature = "signed")]
+impl<S> FromRequestParts<S> for crate::SignedCookies<'static>
+where
+    S: Sync + Send,
+    Key: FromRef<S>,
+{
+    type Rejection = (http::StatusCode, &'static str);
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let cookies = parts.extensions.get::<Cookies>().ok_or((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Can't extract signed cookies. Is `CookieManagerLayer` enabled?",
+        ))?;
+        Ok(signed(cookies, state))
+    }
+}
+
+/// Extracts [`PrivateCookies`](crate::PrivateCookies) using the keys of the layer, or the
+/// [`Key`] of the router state if the layer has no keys.
+///
+/// See the [`SignedCookies`](crate::SignedCookies) extractor for an example.
+#[cfg(feature = "private")]
+impl<S> FromRequestParts<S> for crate::PrivateCookies<'static>
+where
+    S: Sync + Send,
+    Key: FromRef<S>,
+{
+    type Rejection = (http::StatusCode, &'static str);
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let cookies = parts.extensions.get::<Cookies>().ok_or((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Can't extract private cookies. Is `CookieManagerLayer` enabled?",
+        ))?;
+        Ok(private(cookies, state))
+    }
+}
+
+/// Returns a jar using the keys of the layer, or the key of the `state`.
+#[cfg(feature = "signed")]
+fn signed<S>(cookies: &Cookies, state: &S) -> crate::SignedCookies<'static>
+where
+    Key: FromRef<S>,
+{
+    cookies
+        .signed_owned()
+        .unwrap_or_else(|| crate::SignedCookies::new(cookies, KeyRef::Owned(Key::from_ref(state))))
+}
+
+/// Returns a jar using the keys of the layer, or the key of the `state`.
+#[cfg(feature = "private")]
+fn private<S>(cookies: &Cookies, state: &S) -> crate::PrivateCookies<'static>
+where
+    Key: FromRef<S>,
+{
+    cookies
+        .private_owned()
+        .unwrap_or_else(|| crate::PrivateCookies::new(cookies, KeyRef::Owned(Key::from_ref(state))))
+}
+
+#[cfg(feature = "session")]
+impl<S> FromRequestParts<S> for crate::session::Session
+where
+    S: Sync + Send,
+{
+    type Rejection = (http::StatusCode, &'static str);
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        parts.extensions.get::<Self>().cloned().ok_or((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Can't extract session. Is `CookieManagerLayer::with_session` enabled?",
+        ))
+    }
+}
+
+#[cfg(feature = "flash")]
+impl<S> FromRequestParts<S> for crate::flash::Flash
+where
+    S: Sync + Send,
+{
+    type Rejection = (http::StatusCode, &'static str);
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let cookies = Cookies::from_request_parts(parts, state).await?;
+        Self::from_cookies(&cookies).ok_or((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Can't extract flash. Is `CookieManagerLayer::with_key` set?",
+        ))
+    }
+}
+
+#[cfg(feature = "flash")]
+impl<S> FromRequestParts<S> for crate::flash::IncomingFlashes
+where
+    S: Sync + Send,
+{
+    type Rejection = (http::StatusCode, &'static str);
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let cookies = Cookies::from_request_parts(parts, state).await?;
+        Self::take(&cookies).ok_or((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Can't extract flash messages. Is `CookieManagerLayer::with_key` set?",
+        ))
+    }
+}
+
+#[cfg(feature = "csrf")]
+impl<S> FromRequestParts<S> for crate::csrf::CsrfToken
+where
+    S: Sync + Send,
+{
+    type Rejection = (http::StatusCode, &'static str);
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        parts.extensions.get::<Self>().cloned().ok_or((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Can't extract CSRF token. Is `CsrfLayer` enabled?",
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{test_helpers, CookieManagerLayer};
+    use axum::{routing::get, Router};
+    use std::num::ParseIntError;
+
+    struct Visits(u32);
+
+    impl FromStr for Visits {
+        type Err = ParseIntError;
+
+        fn from_str(s: &str) -> Result<Self, Self::Err> {
+            s.parse().map(Self)
+        }
+    }
+
+    impl CookieValue for Visits {
+        const NAME: &'static str = "visits";
+    }
+
+    async fn call(app: Router, cookie: Option<&str>) -> (StatusCode, String) {
+        let res = test_helpers::call(&app, "/", cookie).await;
+        (res.status(), test_helpers::body_string(res).await)
+    }
+
+    #[tokio::test]
+    async fn required() {
+        let app = Router::new()
+            .route(
+                "/",
+                get(|Cookie(visits): Cookie<Visits>| async move { visits.0.to_string() }),
+            )
+            .layer(CookieManagerLayer::new());
+        assert_eq!(
+            call(app.clone(), Some("visits=3")).await,
+            (StatusCode::OK, "3".into())
+        );
+        assert_eq!(
+            call(app.clone(), None).await,
+            (StatusCode::BAD_REQUEST, "Missing cookie `visits`".into())
+        );
+        assert_eq!(
+            call(app, Some("visits=x")).await,
+            (StatusCode::BAD_REQUEST, "Invalid cookie `visits`".into())
+        );
+    }
+
+    #[tokio::test]
+    async fn optional() {
+        let app = Router::new()
+            .route(
+                "/",
+                get(
+                    |OptionalCookie(visits): OptionalCookie<Visits>| async move {
+                        visits.map_or(0, |v| v.0).to_string()
+                    },
+                ),
+            )
+            .layer(CookieManagerLayer::new());
+        assert_eq!(call(app.clone(), None).await, (StatusCode::OK, "0".into()));
+        assert_eq!(
+            call(app.clone(), Some("visits=3")).await,
+            (StatusCode::OK, "3".into())
+        );
+        assert_eq!(call(app, Some("visits=x")).await.0, StatusCode::BAD_REQUEST);
+    }
+
+    #[cfg(feature = "signed")]
+    #[tokio::test]
+    async fn signed() {
+        let key = Key::generate();
+        let app =
+            Router::new()
+                .route(
+                    "/",
+                    get(|SignedCookie(visits): SignedCookie<Visits>| async move {
+                        visits.0.to_string()
+                    }),
+                )
+                .layer(CookieManagerLayer::new())
+                .with_state(key.clone());
+
+        let cookies = Cookies::default();
+        cookies
+            .signed(&key)
+            .add(cookie::Cookie::new(Visits::NAME, "3"));
+        let signed = cookies.get(Visits::NAME).unwrap().to_string();
+        assert_eq!(
+            call(app.clone(), Some(&signed)).await,
+            (StatusCode::OK, "3".into())
+        );
+        assert_eq!(
+            call(app.clone(), Some("visits=3")).await,
+            (
+                StatusCode::UNAUTHORIZED,
+                "Cookie `visits` fails to verify".into()
+            )
+        );
+        assert_eq!(call(app, None).await.0, StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn missing_layer() {
+        let app = Router::new().route("/", get(|_: Cookie<Visits>| async {}));
+        assert_eq!(
+            call(app, Some("visits=3")).await.0,
+            StatusCode::INTERNAL_SERVER_ERROR
+        );
+    }
+}
@@ -0,0 +1,300 @@
+//! One-shot flash messages.
+//!
+//! A message is queued by [`Flash`], e.g. while handling a form submission, and shown once by the
+//! handler of the page the client is redirected to, which extracts [`IncomingFlashes`]. The
+//! messages are kept in a cookie signed by the key set by
+//! [`CookieManagerLayer::with_key`](crate::CookieManagerLayer::with_key), and the cookie is
+//! removed once the messages are read.
+//!
+//! # Example:
+//! ```
+//! use axum::{
+//!     response::Redirect,
+//!     routing::{get, post},
+//!     Router,
+//! };
+//! use tower_cookies::{
+//!     flash::{Flash, IncomingFlashes},
+//!     CookieManagerLayer, Key,
+//! };
+//!
+//! async fn save(flash: Flash) -> Redirect {
+//!     flash.success("Saved");
+//!     Redirect::to("/")
+//! }
+//!
+//! async fn show(flashes: IncomingFlashes) -> String {
+//!     flashes
+//!         .iter()
+//!         .map(|m| format!("{}: {}", m.level(), m.message()))
+//!         .collect::<Vec<_>>()
+//!         .join("\n")
+//! }
+//!
+//! let app: Router = Router::new()
+//!     .route("/", get(show))
+//!     .route("/save", post(save))
+//!     .layer(CookieManagerLayer::new().with_key(Key::generate()));
+//! ```
+
+use crate::{
+    typed::{Codec, Json, TypedCookieError},
+    Cookies, SignedCookies,
+};
+use cookie::Cookie;
+use std::{fmt, slice};
+
+const COOKIE_NAME: &str = "flash";
+
+/// The level of a flash message.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Level {
+    /// Informational message.
+    Info,
+    /// Message about a successful action.
+    Success,
+    /// Warning message.
+    Warning,
+    /// Error message.
+    Error,
+}
+
+impl Level {
+    /// Returns the lowercase name of the level, e.g. to be used as a CSS class.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Info => "info",
+            Self::Success => "success",
+            Self::Warning => "warning",
+            Self::Error => "error",
+        }
+    }
+
+    fn from_str(s: &str) -> Option<Self> {
+        Some(match s {
+            "info" => Self::Info,
+            "success" => Self::Success,
+            "warning" => Self::Warning,
+            "error" => Self::Error,
+            _ => return None,
+        })
+    }
+}
+
+impl fmt::Display for Level {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A flash message.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FlashMessage {
+    level: Level,
+    message: String,
+}
+
+impl FlashMessage {
+    /// Returns the level of the message.
+    pub fn level(&self) -> Level {
+        self.level
+    }
+
+    /// Returns the text of the message.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+/// Queues flash messages to be shown on the next request.
+#[derive(Clone, Debug)]
+pub struct Flash {
+    cookies: SignedCookies<'static>,
+}
+
+impl Flash {
+    /// Creates a writer of flash messages signed by the key set on the layer. Returns [`None`] if
+    /// the layer has no key.
+    pub fn from_cookies(cookies: &Cookies) -> Option<Self> {
+        Some(Self {
+            cookies: cookies.signed_owned()?,
+        })
+    }
+
+    /// Queues a message with the given level.
+    ///
+    /// Messages are appended to the ones queued earlier, including the incoming messages which
+    /// haven't been read during the current request.
+    pub fn push(&self, level: Level, message: impl Into<String>) {
+        let mut messages = read(&self.cookies);
+        messages.push(FlashMessage {
+            level,
+            message: message.into(),
+        });
+        let encoded: Vec<_> = messages
+            .iter()
+            .map(|m| (m.level.as_str(), m.message.as_str()))
+            .collect();
+        if let Ok(cookie) = flash_cookie(&encoded) {
+            self.cookies.add(cookie);
+        }
+    }
+
+    /// Queues an informational message.
+    pub fn info(&self, message: impl Into<String>) {
+        self.push(Level::Info, message)
+    }
+
+    /// Queues a message about a successful action.
+    pub fn success(&self, message: impl Into<String>) {
+        self.push(Level::Success, message)
+    }
+
+    /// Queues a warning message.
+    pub fn warning(&self, message: impl Into<String>) {
+        self.push(Level::Warning, message)
+    }
+
+    /// Queues an error message.
+    pub fn error(&self, message: impl Into<String>) {
+        self.push(Level::Error, message)
+    }
+}
+
+/// Flash messages sent with the current request.
+///
+/// Taking the messages removes the flash cookie, so they are shown only once.
+#[derive(Clone, Debug, Default)]
+pub struct IncomingFlashes {
+    messages: Vec<FlashMessage>,
+}
+
+impl IncomingFlashes {
+    /// Takes the incoming messages, removing the flash cookie. Returns [`None`] if the layer has
+    /// no key.
+    pub fn take(cookies: &Cookies) -> Option<Self> {
+        let cookies = cookies.signed_owned()?;
+        let messages = read(&cookies);
+        if !messages.is_empty() {
+            cookies.remove(Cookie::build(COOKIE_NAME).path("/").build());
+        }
+        Some(Self { messages })
+    }
+
+    /// Returns an iterator over the messages in the order they were queued.
+    pub fn iter(&self) -> slice::Iter<'_, FlashMessage> {
+        self.messages.iter()
+    }
+
+    /// Returns the number of messages.
+    pub fn len(&self) -> usize {
+        self.messages.len()
+    }
+
+    /// Returns `true` if there are no messages.
+    pub fn is_empty(&self) -> bool {
+        self.messages.is_empty()
+    }
+}
+
+impl IntoIterator for IncomingFlashes {
+    type Item = FlashMessage;
+    type IntoIter = std::vec::IntoIter<FlashMessage>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.messages.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a IncomingFlashes {
+    type Item = &'a FlashMessage;
+    type IntoIter = slice::Iter<'a, FlashMessage>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+fn read(cookies: &SignedCookies<'_>) -> Vec<FlashMessage> {
+    let messages: Vec<(String, String)> = cookies.get_typed(COOKIE_NAME).unwrap_or_default();
+    messages
+        .into_iter()
+        .filter_map(|(level, message)| {
+            Some(FlashMessage {
+                level: Level::from_str(&level)?,
+                message,
+            })
+        })
+        .collect()
+}
+
+fn flash_cookie(messages: &[(&str, &str)]) -> Result<Cookie<'static>, TypedCookieError> {
+    Ok(Cookie::build((COOKIE_NAME, Json::encode(messages)?))
+        .path("/")
+        .http_only(true)
+        .build())
+}
+
+#[cfg(all(test, feature = "axum-core"))]
+mod tests {
+    use super::*;
+    use crate::{
+        service::Config,
+        test_helpers::{body_string, call, set_cookie},
+        CookieManagerLayer, KeyRing,
+    };
+    use axum::{routing::get, Router};
+    use cookie::Key;
+    use std::sync::Arc;
+
+    fn app() -> Router {
+        Router::new()
+            .route(
+                "/",
+                get(|flashes: IncomingFlashes| async move {
+                    flashes
+                        .iter()
+                        .map(|m| format!("{}: {}", m.level(), m.message()))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                }),
+            )
+            .route(
+                "/save",
+                get(|flash: Flash| async move {
+                    flash.success("Saved");
+                    flash.warning("Check it");
+                }),
+            )
+            .layer(CookieManagerLayer::new().with_key(Key::from(&[1; 64])))
+    }
+
+    #[tokio::test]
+    async fn show_once() {
+        let app = app();
+        let res = call(&app, "/", None).await;
+        assert_eq!(set_cookie(&res), None);
+
+        let res = call(&app, "/save", None).await;
+        let cookie = set_cookie(&res).unwrap();
+
+        let res = call(&app, "/", Some(&cookie)).await;
+        assert_eq!(set_cookie(&res).unwrap(), "flash=");
+        assert_eq!(body_string(res).await, "success: Saved, warning: Check it");
+    }
+
+    #[test]
+    fn append_unread() {
+        let cookies = Cookies::new(vec![]).with_config(Arc::new(Config {
+            keys: Some(Arc::new(KeyRing::new(Key::generate()))),
+            ..Default::default()
+        }));
+
+        Flash::from_cookies(&cookies).unwrap().info("one");
+        Flash::from_cookies(&cookies).unwrap().error("two");
+        let flashes = IncomingFlashes::take(&cookies).unwrap();
+        let levels: Vec<_> = flashes.iter().map(|m| m.level()).collect();
+        assert_eq!(levels, [Level::Info, Level::Error]);
+        assert!(IncomingFlashes::take(&cookies).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,100 @@
+use cookie::Key;
+use std::sync::Arc;
+
+/// A set of keys allowing to rotate the key of signed and private cookies.
+///
+/// The primary key is used to sign and encrypt cookies, while the retired keys are only used to
+/// verify and decrypt cookies issued before the rotation. Optionally, a cookie read with a retired
+/// key can be re-issued under the primary key, so users are moved to the new key as they visit the
+/// site instead of being logged out at once.
+#[derive(Clone, Debug)]
+pub struct KeyRing {
+    primary: Key,
+    retired: Vec<Key>,
+    reissue: bool,
+}
+
+impl KeyRing {
+    /// Creates a key ring with the `primary` key and no retired keys.
+    pub fn new(primary: Key) -> Self {
+        Self {
+            primary,
+            retired: Vec::new(),
+            reissue: false,
+        }
+    }
+
+    /// Adds a retired `key` accepted for verification and decryption of existing cookies.
+    pub fn with_retired(mut self, key: Key) -> Self {
+        self.retired.push(key);
+        self
+    }
+
+    /// Sets whether a cookie read with a retired key is re-issued under the primary key.
+    ///
+    /// The client doesn't send cookie attributes back, so the re-issued cookie only gets the
+    /// default attributes configured on [`CookieManagerLayer`](crate::CookieManagerLayer).
+    pub fn reissue(mut self, value: bool) -> Self {
+        self.reissue = value;
+        self
+    }
+
+    /// Returns the primary key.
+    pub fn primary(&self) -> &Key {
+        &self.primary
+    }
+
+    /// Returns the retired keys.
+    pub fn retired(&self) -> &[Key] {
+        &self.retired
+    }
+}
+
+impl From<Key> for KeyRing {
+    fn from(key: Key) -> Self {
+        Self::new(key)
+    }
+}
+
+/// Keys used by the child jars, either borrowed from the caller, shared with the layer or
+/// extracted from the router state.
+#[derive(Clone, Debug)]
+pub(crate) enum KeyRef<'a> {
+    Key(&'a Key),
+    Ring(&'a KeyRing),
+    Shared(Arc<KeyRing>),
+    #[cfg_attr(not(feature = "axum-core"), allow(dead_code))]
+    Owned(Key),
+}
+
+impl KeyRef<'_> {
+    fn ring(&self) -> Option<&KeyRing> {
+        match self {
+            Self::Key(_) | Self::Owned(_) => None,
+            Self::Ring(ring) => Some(ring),
+            Self::Shared(ring) => Some(ring),
+        }
+    }
+
+    /// Returns the key used to sign or encrypt cookies.
+    pub(crate) fn primary(&self) -> &Key {
+        match self {
+            Self::Key(key) => key,
+            Self::Owned(key) => key,
+            Self::Ring(ring) => &ring.primary,
+            Self::Shared(ring) => &ring.primary,
+        }
+    }
+
+    /// Returns the keys only used to verify or decrypt cookies.
+    pub(crate) fn retired(&self) -> &[Key] {
+        self.ring()
+            .map(|r| r.retired.as_slice())
+            .unwrap_or_default()
+    }
+
+    /// Returns `true` if cookies read with a retired key should be re-issued.
+    pub(crate) fn reissue(&self) -> bool {
+        self.ring().is_some_and(|r| r.reissue)
+    }
+}
@@ -7,7 +7,8 @@
 use cookie::CookieJar;
 use http::HeaderValue;
 use parking_lot::Mutex;
-use std::sync::Arc;
+use service::Config;
+use std::{collections::HashMap, sync::Arc};
 
 #[doc(inline)]
 pub use self::service::{CookieManager, CookieManagerLayer};
@@ -18,17 +19,41 @@ pub use self::signed::SignedCookies;
 #[cfg(feature = "private")]
 pub use self::private::PrivateCookies;
 
+#[cfg(any(feature = "signed", feature = "private"))]
+pub use self::key::KeyRing;
+
 #[cfg(any(feature = "signed", feature = "private"))]
 pub use cookie::Key;
 
 pub use cookie::Cookie;
 
+#[cfg(feature = "derive")]
+pub use tower_cookies_derive::Cookie;
+
+pub use self::changes::CookieChanges;
+
+pub use self::parse::{ParseError, ParseErrorReason};
+
+pub use self::validate::{InvalidCookie, InvalidCookieReason};
+
 #[doc(inline)]
 pub use cookie;
 
+// Lets the derive macro output, which refers to `::tower_cookies`, compile in the tests
+#[cfg(all(test, feature = "derive"))]
+extern crate self as tower_cookies;
+
 #[cfg(feature = "axum-core")]
 #[cfg_attr(docsrs, doc(cfg(feature = "axum-core")))]
-mod extract;
+pub mod extract;
+
+mod changes;
+mod chunk;
+mod parse;
+mod validate;
+
+#[cfg(any(feature = "signed", feature = "private"))]
+mod key;
 
 #[cfg(feature = "signed")]
 mod signed;
@@ -36,12 +61,38 @@ mod signed;
 #[cfg(feature = "private")]
 mod private;
 
+pub mod prefix;
 pub mod service;
 
+#[cfg(feature = "psl")]
+pub mod psl;
+
+#[cfg(feature = "serde")]
+pub mod typed;
+
+#[cfg(feature = "session")]
+pub mod session;
+
+#[cfg(feature = "flash")]
+pub mod flash;
+
+#[cfg(feature = "csrf")]
+pub mod csrf;
+
+#[cfg(feature = "client")]
+pub mod client;
+
+#[cfg(feature = "test-util")]
+pub mod test;
+
+#[cfg(all(test, feature = "axum-core"))]
+mod test_helpers;
+
 /// A parsed on-demand cookie jar.
 #[derive(Clone, Debug, Default)]
 pub struct Cookies {
     inner: Arc<Mutex<Inner>>,
+    config: Arc<Config>,
 }
 
 impl Cookies {
@@ -52,44 +103,170 @@ impl Cookies {
         };
         Self {
             inner: Arc::new(Mutex::new(inner)),
+            config: Default::default(),
         }
     }
 
+    fn with_config(mut self, config: Arc<Config>) -> Self {
+        self.inner.lock().chunked = config.chunk_size.is_some();
+        self.config = config;
+        self
+    }
+
     /// Adds [`Cookie`] to this jar. If a [`Cookie`] with the same name already exists, it is
     /// replaced with provided cookie.
+    ///
+    /// [`Cookie`]: struct@Cookie
     pub fn add(&self, cookie: Cookie<'static>) {
         let mut inner = self.inner.lock();
         inner.changed = true;
         inner.jar().add(cookie);
     }
 
+    /// Adds [`Cookie`] to this jar like [`Cookies::add`], if it can be sent to the client as is.
+    ///
+    /// Cookies with control characters are dropped when the response is sent, and cookies with
+    /// `;` in the name or value are truncated by the client, so this method reports them early.
+    ///
+    /// # Example:
+    /// ```
+    /// use tower_cookies::{Cookie, Cookies, InvalidCookieReason};
+    ///
+    /// let cookies = Cookies::default();
+    /// let error = cookies.try_add(Cookie::new("name", "a;b")).unwrap_err();
+    /// assert_eq!(error.reason(), InvalidCookieReason::InvalidValue);
+    /// assert!(cookies.get("name").is_none());
+    /// ```
+    ///
+    /// [`Cookie`]: struct@Cookie
+    pub fn try_add(&self, cookie: Cookie<'static>) -> Result<(), InvalidCookie> {
+        validate::validate(&cookie)?;
+        self.add(cookie);
+        Ok(())
+    }
+
     /// Returns the [`Cookie`] with the given name. Returns [`None`] if it doesn't exist.
+    ///
+    /// If the client sent several cookies with the same name, e.g. set for different paths or
+    /// domains, the first one is returned. Browsers send the cookies with longer paths first, so
+    /// it's the most specific one. Use [`Cookies::get_all`] to get all of them.
+    ///
+    /// [`Cookie`]: struct@Cookie
     pub fn get(&self, name: &str) -> Option<Cookie<'_>> {
         let mut inner = self.inner.lock();
         inner.jar().get(name).cloned()
     }
 
+    /// Returns all the cookies with the given name sent by the client, in the order of the
+    /// `Cookie` headers.
+    ///
+    /// Unlike [`Cookies::get`], the result isn't affected by the cookies added or removed during
+    /// the request.
+    ///
+    /// # Example:
+    /// ```
+    /// use axum::{routing::get, Router};
+    /// use tower_cookies::{Cookie, CookieManagerLayer, Cookies};
+    ///
+    /// async fn handler(cookies: Cookies) {
+    ///     // A cookie left by the old `/app` path shadowing the new `/` one
+    ///     if cookies.get_all("id").len() > 1 {
+    ///         cookies.remove(Cookie::build("id").path("/app").build());
+    ///     }
+    /// }
+    ///
+    /// let app: Router = Router::new()
+    ///     .route("/app", get(handler))
+    ///     .layer(CookieManagerLayer::new());
+    /// ```
+    pub fn get_all(&self, name: &str) -> Vec<Cookie<'static>> {
+        let mut inner = self.inner.lock();
+        inner.jar();
+        inner
+            .request_cookies
+            .iter()
+            .filter(|c| c.name() == name)
+            .cloned()
+            .collect()
+    }
+
     /// Removes [`Cookie`] from this jar.
     ///
     /// **To properly generate the removal cookie, `cookie` must contain the same `path` and
     /// `domain` as the cookie that was initially set.** In particular, this means that passing a
     ///  cookie from a browser to this method won't work because browsers don't set the cookie's
     /// `path` attribute.
+    ///
+    /// [`Cookie`]: struct@Cookie
     pub fn remove(&self, cookie: Cookie<'static>) {
         let mut inner = self.inner.lock();
         inner.changed = true;
         inner.jar().remove(cookie);
     }
 
+    /// Removes the cookie named `name` from this jar, using the attributes registered by
+    /// [`CookieManagerLayer::register`] to build the removal cookie.
+    ///
+    /// Without a registered cookie, the removal cookie only has the default attributes of the
+    /// layer, like a cookie removed by [`Cookies::remove`] with just a name.
+    ///
+    /// # Example:
+    /// ```
+    /// use axum::{routing::get, Router};
+    /// use tower_cookies::{Cookie, CookieManagerLayer, Cookies};
+    ///
+    /// async fn logout(cookies: Cookies) {
+    ///     cookies.remove_by_name("session");
+    /// }
+    ///
+    /// let app: Router = Router::new()
+    ///     .route("/logout", get(logout))
+    ///     .layer(CookieManagerLayer::new().register(Cookie::build("session").path("/app")));
+    /// ```
+    pub fn remove_by_name(&self, name: &str) {
+        let mut cookie = Cookie::new(name.to_owned(), "");
+        self.config.apply_registered(&mut cookie);
+        self.remove(cookie);
+    }
+
     /// Returns all the [`Cookie`]s present in this jar.
     ///
     /// This method collects [`Cookie`]s into a vector instead of iterating through them to
     /// minimize the mutex locking time.
+    ///
+    /// [`Cookie`]: struct@Cookie
     pub fn list(&self) -> Vec<Cookie<'_>> {
         let mut inner = self.inner.lock();
         inner.jar().iter().cloned().collect()
     }
 
+    /// Returns the fragments of the `Cookie` request headers which failed to parse and were
+    /// skipped.
+    ///
+    /// Requests with such fragments can be rejected by
+    /// [`RejectMalformedCookiesLayer`](service::RejectMalformedCookiesLayer).
+    ///
+    /// # Example:
+    /// ```
+    /// use axum::{routing::get, Router};
+    /// use tower_cookies::{CookieManagerLayer, Cookies};
+    ///
+    /// async fn handler(cookies: Cookies) {
+    ///     for error in cookies.parse_errors() {
+    ///         eprintln!("broken client: {error}");
+    ///     }
+    /// }
+    ///
+    /// let app: Router = Router::new()
+    ///     .route("/", get(handler))
+    ///     .layer(CookieManagerLayer::new());
+    /// ```
+    pub fn parse_errors(&self) -> Vec<ParseError> {
+        let mut inner = self.inner.lock();
+        inner.jar();
+        inner.parse_errors.clone()
+    }
+
     /// Returns a child [`SignedCookies`] jar for interactions with signed by the `key` cookies.
     ///
     /// # Example:
@@ -109,7 +286,57 @@ impl Cookies {
     /// ```
     #[cfg(feature = "signed")]
     pub fn signed<'a>(&self, key: &'a cookie::Key) -> SignedCookies<'a> {
-        SignedCookies::new(self, key)
+        SignedCookies::new(self, key::KeyRef::Key(key))
+    }
+
+    /// Returns a child [`SignedCookies`] jar signing cookies with the primary key of the `ring`
+    /// and verifying them with any of its keys.
+    ///
+    /// # Example:
+    /// ```
+    /// use cookie::{Cookie, Key};
+    /// use tower_cookies::{Cookies, KeyRing};
+    ///
+    /// let old_key = Key::generate();
+    /// let cookies = Cookies::default();
+    /// cookies.signed(&old_key).add(Cookie::new("foo", "bar"));
+    ///
+    /// let ring = KeyRing::new(Key::generate())
+    ///     .with_retired(old_key)
+    ///     .reissue(true);
+    /// assert_eq!(cookies.signed_with_ring(&ring).get("foo").unwrap().value(), "bar");
+    /// ```
+    #[cfg(feature = "signed")]
+    pub fn signed_with_ring<'a>(&self, ring: &'a KeyRing) -> SignedCookies<'a> {
+        SignedCookies::new(self, key::KeyRef::Ring(ring))
+    }
+
+    /// Returns a child [`SignedCookies`] jar using the keys set by [`CookieManagerLayer::with_key`]
+    /// or [`CookieManagerLayer::with_key_ring`]. Returns [`None`] if the layer has no keys.
+    ///
+    /// Unlike [`Cookies::signed`], the returned jar doesn't borrow the key, so it can be returned
+    /// from extractors and stored in structs.
+    ///
+    /// # Example:
+    /// ```
+    /// use cookie::Key;
+    /// use tower_cookies::{CookieManagerLayer, Cookies, SignedCookies};
+    ///
+    /// struct Auth {
+    ///     cookies: SignedCookies<'static>,
+    /// }
+    ///
+    /// let layer = CookieManagerLayer::new().with_key(Key::generate());
+    /// # let _ = layer;
+    /// # let cookies = Cookies::default();
+    /// // `cookies` are taken from a request passed through the `layer`
+    /// let auth = cookies.signed_owned().map(|cookies| Auth { cookies });
+    /// # assert!(auth.is_none());
+    /// ```
+    #[cfg(feature = "signed")]
+    pub fn signed_owned(&self) -> Option<SignedCookies<'static>> {
+        let key = self.config.keys.clone()?;
+        Some(SignedCookies::new(self, key::KeyRef::Shared(key)))
     }
 
     /// Returns a child [`PrivateCookies`] jar for encrypting and decrypting cookies.
@@ -131,7 +358,41 @@ impl Cookies {
     /// ```
     #[cfg(feature = "private")]
     pub fn private<'a>(&self, key: &'a cookie::Key) -> PrivateCookies<'a> {
-        PrivateCookies::new(self, key)
+        PrivateCookies::new(self, key::KeyRef::Key(key))
+    }
+
+    /// Returns a child [`PrivateCookies`] jar encrypting cookies with the primary key of the
+    /// `ring` and decrypting them with any of its keys.
+    #[cfg(feature = "private")]
+    pub fn private_with_ring<'a>(&self, ring: &'a KeyRing) -> PrivateCookies<'a> {
+        PrivateCookies::new(self, key::KeyRef::Ring(ring))
+    }
+
+    /// Returns a child [`PrivateCookies`] jar using the keys set by
+    /// [`CookieManagerLayer::with_key`] or [`CookieManagerLayer::with_key_ring`]. Returns [`None`]
+    /// if the layer has no keys.
+    ///
+    /// Unlike [`Cookies::private`], the returned jar doesn't borrow the key, so it can be returned
+    /// from extractors and stored in structs.
+    ///
+    /// # Example:
+    /// ```
+    /// use axum::{routing::get, Router};
+    /// use tower_cookies::{Cookie, CookieManagerLayer, Cookies, Key};
+    ///
+    /// async fn handler(cookies: Cookies) {
+    ///     let private = cookies.private_owned().unwrap();
+    ///     private.add(Cookie::new("secret", "value"));
+    /// }
+    ///
+    /// let app: Router = Router::new()
+    ///     .route("/", get(handler))
+    ///     .layer(CookieManagerLayer::new().with_key(Key::generate()));
+    /// ```
+    #[cfg(feature = "private")]
+    pub fn private_owned(&self) -> Option<PrivateCookies<'static>> {
+        let key = self.config.keys.clone()?;
+        Some(PrivateCookies::new(self, key::KeyRef::Shared(key)))
     }
 }
 
@@ -140,22 +401,37 @@ struct Inner {
     headers: Vec<HeaderValue>,
     jar: Option<CookieJar>,
     changed: bool,
+    /// Whether chunked cookies should be joined
+    chunked: bool,
+    /// The number of chunks of every incoming cookie
+    chunks: HashMap<String, usize>,
+    parse_errors: Vec<ParseError>,
+    /// All the request cookies in the header order, including the ones with duplicate names
+    request_cookies: Vec<Cookie<'static>>,
 }
 
 impl Inner {
     fn jar(&mut self) -> &mut CookieJar {
         if self.jar.is_none() {
+            let (mut cookies, errors) = parse::parse_headers(&self.headers);
+            self.parse_errors = errors;
+            #[cfg(feature = "tracing")]
+            tracing::debug!(
+                names = ?cookies.iter().map(cookie::Cookie::name).collect::<Vec<_>>(),
+                "parsed request cookies"
+            );
+            if self.chunked {
+                (cookies, self.chunks) = chunk::join(cookies);
+            }
             let mut jar = CookieJar::new();
-            for header in &self.headers {
-                if let Ok(header_str) = std::str::from_utf8(header.as_bytes()) {
-                    for cookie_str in header_str.split(';') {
-                        if let Ok(cookie) = cookie::Cookie::parse_encoded(cookie_str.to_owned()) {
-                            jar.add_original(cookie);
-                        }
-                    }
+            for cookie in &cookies {
+                // Browsers send cookies with longer paths first, so keep the most specific one
+                if jar.get(cookie.name()).is_none() {
+                    jar.add_original(cookie.clone());
                 }
             }
             self.jar = Some(jar);
+            self.request_cookies = cookies;
         }
         self.jar.as_mut().unwrap()
     }
@@ -163,11 +439,10 @@ impl Inner {
 
 #[cfg(all(test, feature = "axum-core"))]
 mod tests {
-    use crate::{CookieManagerLayer, Cookies};
+    use crate::{prefix::PrefixPolicy, test_helpers::body_string, CookieManagerLayer, Cookies};
     use axum::{body::Body, routing::get, Router};
-    use cookie::Cookie;
-    use http::{header, Request};
-    use http_body_util::BodyExt;
+    use cookie::{Cookie, SameSite};
+    use http::{header, Request, StatusCode};
     use tower::ServiceExt;
 
     fn app() -> Router {
@@ -200,11 +475,6 @@ mod tests {
             .layer(CookieManagerLayer::new())
     }
 
-    async fn body_string(body: Body) -> String {
-        let bytes = body.collect().await.unwrap().to_bytes();
-        String::from_utf8_lossy(&bytes).into()
-    }
-
     #[tokio::test]
     async fn read_cookies() {
         let req = Request::builder()
@@ -213,7 +483,7 @@ mod tests {
             .body(Body::empty())
             .unwrap();
         let res = app().oneshot(req).await.unwrap();
-        assert_eq!(body_string(res.into_body()).await, "bar=2, foo=1");
+        assert_eq!(body_string(res).await, "bar=2, foo=1");
     }
 
     #[tokio::test]
@@ -225,7 +495,7 @@ mod tests {
             .body(Body::empty())
             .unwrap();
         let res = app().oneshot(req).await.unwrap();
-        assert_eq!(body_string(res.into_body()).await, "bar=2, foo=1");
+        assert_eq!(body_string(res).await, "bar=2, foo=1");
     }
 
     #[tokio::test]
@@ -254,4 +524,424 @@ mod tests {
         assert!(hdr.starts_with("foo=; Max-Age=0"));
         assert_eq!(hdrs.next(), None);
     }
+
+    #[tokio::test]
+    async fn default_attributes() {
+        let app = Router::new()
+            .route(
+                "/",
+                get(|cookies: Cookies| async move {
+                    cookies.add(Cookie::new("foo", "1"));
+                    cookies.add(
+                        Cookie::build(("bar", "2"))
+                            .path("/bar")
+                            .secure(false)
+                            .build(),
+                    );
+                }),
+            )
+            .layer(
+                CookieManagerLayer::new()
+                    .secure(true)
+                    .http_only(true)
+                    .same_site(SameSite::Lax)
+                    .path("/"),
+            );
+        let req = Request::builder().uri("/").body(Body::empty()).unwrap();
+        let res = app.oneshot(req).await.unwrap();
+        let mut hdrs: Vec<_> = res.headers().get_all(header::SET_COOKIE).iter().collect();
+        hdrs.sort();
+        assert_eq!(
+            hdrs,
+            [
+                "bar=2; HttpOnly; SameSite=Lax; Path=/bar",
+                "foo=1; HttpOnly; SameSite=Lax; Secure; Path=/"
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn chunked_cookies() {
+        let app = Router::new()
+            .route(
+                "/",
+                get(|cookies: Cookies| async move {
+                    let value = cookies.get("foo").unwrap().value().to_owned();
+                    cookies.add(Cookie::new("foo", format!("{value}!")));
+                    value
+                }),
+            )
+            .layer(CookieManagerLayer::new().chunk_size(2));
+        let req = Request::builder()
+            .uri("/")
+            .header(header::COOKIE, "foo.1=cd; foo.0=ab")
+            .body(Body::empty())
+            .unwrap();
+        let res = app.oneshot(req).await.unwrap();
+        let hdrs: Vec<_> = res.headers().get_all(header::SET_COOKIE).iter().collect();
+        assert_eq!(hdrs, ["foo.0=ab", "foo.1=cd", "foo.2=!"]);
+        assert_eq!(body_string(res).await, "abcd");
+    }
+
+    #[tokio::test]
+    async fn prefix_policy() {
+        let app = Router::new()
+            .route(
+                "/",
+                get(|cookies: Cookies| async move {
+                    cookies.add(Cookie::new("__Host-foo", "1"));
+                    cookies.add(Cookie::new("__Secure-bar", "2"));
+                    cookies.add(Cookie::new("baz", "3"));
+                }),
+            )
+            .layer(CookieManagerLayer::new().prefix_policy(PrefixPolicy::Reject));
+        let req = Request::builder().uri("/").body(Body::empty()).unwrap();
+        let res = app.oneshot(req).await.unwrap();
+        let hdrs: Vec<_> = res.headers().get_all(header::SET_COOKIE).iter().collect();
+        assert_eq!(hdrs, ["baz=3"]);
+    }
+
+    #[cfg(feature = "psl")]
+    #[tokio::test]
+    async fn public_suffix_domain() {
+        let invalid = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let hook_invalid = invalid.clone();
+        let app = Router::new()
+            .route(
+                "/",
+                get(|cookies: Cookies| async move {
+                    cookies.add(Cookie::build(("foo", "1")).domain("co.uk").build());
+                    cookies.add(Cookie::build(("bar", "2")).domain("a.co.uk").build());
+                    cookies.add(Cookie::build(("baz", "3")).domain("localhost").build());
+                }),
+            )
+            .layer(CookieManagerLayer::new().on_invalid_cookie(move |error| {
+                hook_invalid.lock().unwrap().push(error.name().to_owned());
+            }));
+        let req = Request::builder().uri("/").body(Body::empty()).unwrap();
+        let res = app.oneshot(req).await.unwrap();
+        let mut hdrs: Vec<_> = res.headers().get_all(header::SET_COOKIE).iter().collect();
+        hdrs.sort();
+        assert_eq!(hdrs, ["bar=2; Domain=a.co.uk", "baz=3", "foo=1"]);
+        let mut invalid = invalid.lock().unwrap().clone();
+        invalid.sort();
+        assert_eq!(invalid, ["baz", "foo"]);
+    }
+
+    #[cfg(feature = "tracing")]
+    #[tokio::test]
+    async fn tracing_redacts_values() {
+        use std::sync::{Arc, Mutex};
+
+        #[derive(Clone, Default)]
+        struct Output(Arc<Mutex<Vec<u8>>>);
+
+        impl std::io::Write for Output {
+            fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+                self.0.lock().unwrap().write(buf)
+            }
+
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let output = Output::default();
+        let writer = output.clone();
+        let subscriber = tracing_subscriber::fmt()
+            .with_max_level(tracing::Level::DEBUG)
+            .with_writer(move || writer.clone())
+            .finish();
+        let _guard = tracing::subscriber::set_default(subscriber);
+
+        let req = Request::builder()
+            .uri("/add")
+            .header(header::COOKIE, "foo=1")
+            .body(Body::empty())
+            .unwrap();
+        app().oneshot(req).await.unwrap();
+
+        let output = String::from_utf8(output.0.lock().unwrap().clone()).unwrap();
+        assert!(output.contains("sending cookie"));
+        assert!(output.contains("name=\"baz\""));
+        assert!(output.contains("[redacted]"));
+        assert!(!output.contains("value=\"3\""));
+    }
+
+    #[tokio::test]
+    async fn strict() {
+        let req = || {
+            Request::builder()
+                .uri("/list")
+                .header(header::COOKIE, "foo=1; bar")
+                .body(Body::empty())
+                .unwrap()
+        };
+        let res = app().oneshot(req()).await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(body_string(res).await, "foo=1");
+
+        let app = Router::new()
+            .route("/list", get(|| async {}))
+            .layer(crate::service::RejectMalformedCookiesLayer::new());
+        // Without `Cookies`, the request is passed through
+        let res = app.clone().oneshot(req()).await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        let res = app
+            .layer(CookieManagerLayer::new())
+            .oneshot(req())
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn invalid_cookie_hook() {
+        let invalid = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let hook_invalid = invalid.clone();
+        let app = Router::new()
+            .route(
+                "/",
+                get(|cookies: Cookies| async move {
+                    cookies.add(Cookie::new("foo", "1\n"));
+                    cookies.add(Cookie::new("bar", "2"));
+                }),
+            )
+            .layer(CookieManagerLayer::new().on_invalid_cookie(move |error| {
+                hook_invalid.lock().unwrap().push(error.to_string());
+            }));
+        let req = Request::builder().uri("/").body(Body::empty()).unwrap();
+        let res = app.oneshot(req).await.unwrap();
+        let hdrs: Vec<_> = res.headers().get_all(header::SET_COOKIE).iter().collect();
+        assert_eq!(hdrs, ["bar=2"]);
+        assert_eq!(
+            *invalid.lock().unwrap(),
+            [
+                "invalid cookie `foo`: the value contains characters not allowed in it, \
+              percent-encode the value"
+            ]
+        );
+    }
+
+    #[test]
+    fn duplicate_names() {
+        let cookies = Cookies::new(vec![
+            header::HeaderValue::from_static("id=a; foo=1"),
+            header::HeaderValue::from_static("id=b"),
+        ]);
+        assert_eq!(cookies.get("id").unwrap().value(), "a");
+        let values: Vec<_> = cookies
+            .get_all("id")
+            .iter()
+            .map(|c| c.value().to_owned())
+            .collect();
+        assert_eq!(values, ["a", "b"]);
+        assert_eq!(cookies.list().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn registered_cookies() {
+        let app = Router::new()
+            .route(
+                "/add",
+                get(|cookies: Cookies| async move {
+                    cookies.add(Cookie::new("foo", "1"));
+                }),
+            )
+            .route(
+                "/remove",
+                get(|cookies: Cookies| async move {
+                    cookies.remove_by_name("foo");
+                }),
+            )
+            .layer(
+                CookieManagerLayer::new()
+                    .path("/")
+                    .register(Cookie::build("foo").path("/app").domain("example.com")),
+            );
+        let req = Request::builder().uri("/add").body(Body::empty()).unwrap();
+        let res = app.clone().oneshot(req).await.unwrap();
+        let hdr = res.headers().get(header::SET_COOKIE).unwrap();
+        assert_eq!(hdr, "foo=1; Path=/app; Domain=example.com");
+
+        let req = Request::builder()
+            .uri("/remove")
+            .header(header::COOKIE, "foo=1")
+            .body(Body::empty())
+            .unwrap();
+        let res = app.oneshot(req).await.unwrap();
+        let hdr = res
+            .headers()
+            .get(header::SET_COOKIE)
+            .unwrap()
+            .to_str()
+            .unwrap();
+        assert!(hdr.starts_with("foo=; Path=/app; Domain=example.com; Max-Age=0"));
+    }
+
+    #[cfg(all(feature = "signed", feature = "private"))]
+    #[tokio::test]
+    async fn key_from_state() {
+        use crate::{Key, KeyRing, PrivateCookies, SignedCookies};
+
+        let key = Key::generate();
+        let app = Router::new()
+            .route(
+                "/",
+                get(
+                    |signed: SignedCookies<'static>, private: PrivateCookies<'static>| async move {
+                        signed.add(Cookie::new("signed", "1"));
+                        private.add(Cookie::new("private", "2"));
+                    },
+                ),
+            )
+            .layer(CookieManagerLayer::new())
+            .with_state(key.clone());
+        let req = Request::builder().uri("/").body(Body::empty()).unwrap();
+        let res = app.oneshot(req).await.unwrap();
+        let cookies = Cookies::new(
+            res.headers()
+                .get_all(header::SET_COOKIE)
+                .iter()
+                .cloned()
+                .collect(),
+        );
+        assert_eq!(cookies.signed(&key).get("signed").unwrap().value(), "1");
+        assert_eq!(cookies.private(&key).get("private").unwrap().value(), "2");
+
+        // The keys of the layer take precedence, so the retired ones are accepted
+        let old = Key::generate();
+        let mut jar = cookie::CookieJar::new();
+        jar.signed_mut(&old).add(Cookie::new("signed", "old"));
+        let app = Router::new()
+            .route(
+                "/",
+                get(|signed: SignedCookies<'static>| async move {
+                    signed
+                        .get("signed")
+                        .map(|c| c.value().to_owned())
+                        .unwrap_or_default()
+                }),
+            )
+            .layer(
+                CookieManagerLayer::new()
+                    .with_key_ring(KeyRing::new(key.clone()).with_retired(old)),
+            )
+            .with_state(Key::generate());
+        let req = Request::builder()
+            .uri("/")
+            .header(
+                header::COOKIE,
+                jar.get("signed").unwrap().stripped().to_string(),
+            )
+            .body(Body::empty())
+            .unwrap();
+        let res = app.oneshot(req).await.unwrap();
+        assert_eq!(body_string(res).await, "old");
+
+        let app = Router::new()
+            .route("/", get(|_: SignedCookies<'static>| async {}))
+            .with_state(key);
+        let req = Request::builder().uri("/").body(Body::empty()).unwrap();
+        let res = app.oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(
+            body_string(res).await,
+            "Can't extract signed cookies. Is `CookieManagerLayer` enabled?"
+        );
+    }
+
+    #[tokio::test]
+    async fn set_cookie_conflicts() {
+        use crate::service::ConflictPolicy;
+
+        for (policy, expected, a) in [
+            (
+                ConflictPolicy::KeepBoth,
+                &["a=handler", "b=handler", "a=jar"][..],
+                "jar",
+            ),
+            (ConflictPolicy::JarWins, &["b=handler", "a=jar"][..], "jar"),
+            (
+                ConflictPolicy::HandlerWins,
+                &["a=handler", "b=handler"][..],
+                "handler",
+            ),
+        ] {
+            let app = Router::new()
+                .route(
+                    "/",
+                    get(|cookies: Cookies| async move {
+                        cookies.add(Cookie::new("a", "jar"));
+                        axum::response::AppendHeaders([
+                            (header::SET_COOKIE, "a=handler"),
+                            (header::SET_COOKIE, "b=handler"),
+                        ])
+                    }),
+                )
+                .layer(CookieManagerLayer::new().conflict_policy(policy));
+            let req = Request::builder().uri("/").body(Body::empty()).unwrap();
+            let res = app.oneshot(req).await.unwrap();
+            let hdrs: Vec<_> = res.headers().get_all(header::SET_COOKIE).iter().collect();
+            assert_eq!(hdrs, expected, "{policy:?}");
+
+            let cookies = res.extensions().get::<Cookies>().unwrap();
+            assert_eq!(cookies.get("a").unwrap().value(), a, "{policy:?}");
+            assert_eq!(cookies.get("b").unwrap().value(), "handler", "{policy:?}");
+        }
+    }
+
+    #[tokio::test]
+    async fn cache_headers() {
+        use crate::service::{CacheControl, SkipCacheHeaders};
+        use axum::Extension;
+
+        let app = Router::new()
+            .route(
+                "/set",
+                get(|cookies: Cookies| async move {
+                    cookies.add(Cookie::new("foo", "1"));
+                    [(header::CACHE_CONTROL, "public, max-age=60")]
+                }),
+            )
+            .route("/none", get(|| async {}))
+            .route(
+                "/handler",
+                get(|| async { [(header::SET_COOKIE, "foo=1")] }),
+            )
+            .route(
+                "/skip",
+                get(|cookies: Cookies| async move {
+                    cookies.add(Cookie::new("foo", "1"));
+                    Extension(SkipCacheHeaders)
+                }),
+            )
+            .layer(
+                CookieManagerLayer::new()
+                    .cache_control(CacheControl::Private)
+                    .vary_cookie(true),
+            );
+        let call = |uri| {
+            let req = Request::builder().uri(uri).body(Body::empty()).unwrap();
+            app.clone().oneshot(req)
+        };
+
+        let res = call("/set").await.unwrap();
+        assert_eq!(res.headers()[header::CACHE_CONTROL], "max-age=60, private");
+        assert_eq!(res.headers()[header::VARY], "Cookie");
+
+        let res = call("/handler").await.unwrap();
+        assert_eq!(res.headers()[header::CACHE_CONTROL], "private");
+        assert_eq!(res.headers()[header::VARY], "Cookie");
+
+        for uri in ["/none", "/skip"] {
+            let res = call(uri).await.unwrap();
+            assert!(res.headers().get(header::CACHE_CONTROL).is_none(), "{uri}");
+            assert!(res.headers().get(header::VARY).is_none(), "{uri}");
+            assert!(
+                res.extensions().get::<SkipCacheHeaders>().is_none(),
+                "{uri}"
+            );
+        }
+    }
 }
@@ -0,0 +1,120 @@
+use cookie::Cookie;
+use http::HeaderValue;
+use std::{error::Error, fmt};
+
+/// A fragment of a `Cookie` request header which failed to parse.
+///
+/// Returned by [`Cookies::parse_errors`](crate::Cookies::parse_errors).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseError {
+    fragment: String,
+    reason: ParseErrorReason,
+}
+
+/// The reason a fragment of a `Cookie` header failed to parse.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ParseErrorReason {
+    /// The header isn't valid UTF-8, so none of its cookies are parsed.
+    NonUtf8Header,
+    /// The fragment isn't a valid cookie.
+    Cookie(cookie::ParseError),
+}
+
+impl ParseError {
+    /// Returns the raw fragment. Invalid UTF-8 sequences of non-UTF-8 headers are replaced with
+    /// `U+FFFD`.
+    pub fn fragment(&self) -> &str {
+        &self.fragment
+    }
+
+    /// Returns the reason the fragment failed to parse.
+    pub fn reason(&self) -> ParseErrorReason {
+        self.reason
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.reason {
+            ParseErrorReason::NonUtf8Header => write!(f, "non-UTF-8 cookie header"),
+            ParseErrorReason::Cookie(error) => write!(f, "malformed cookie: {error}"),
+        }?;
+        write!(f, " `{}`", self.fragment)
+    }
+}
+
+impl Error for ParseError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match &self.reason {
+            ParseErrorReason::NonUtf8Header => None,
+            ParseErrorReason::Cookie(error) => Some(error),
+        }
+    }
+}
+
+/// Parses the `Cookie` request headers, returning the cookies and the fragments which failed to
+/// parse. Empty fragments, e.g. after a trailing `;`, are ignored.
+pub(crate) fn parse_headers(headers: &[HeaderValue]) -> (Vec<Cookie<'static>>, Vec<ParseError>) {
+    let mut cookies = Vec::new();
+    let mut errors = Vec::new();
+    for header in headers {
+        let Ok(header_str) = std::str::from_utf8(header.as_bytes()) else {
+            #[cfg(feature = "tracing")]
+            tracing::debug!("skipped a non-UTF-8 Cookie header");
+            errors.push(ParseError {
+                fragment: String::from_utf8_lossy(header.as_bytes()).into_owned(),
+                reason: ParseErrorReason::NonUtf8Header,
+            });
+            continue;
+        };
+        for cookie_str in header_str.split(';') {
+            if cookie_str.trim().is_empty() {
+                continue;
+            }
+            match Cookie::parse_encoded(cookie_str.to_owned()) {
+                Ok(cookie) => cookies.push(cookie),
+                Err(error) => {
+                    #[cfg(feature = "tracing")]
+                    tracing::debug!(%error, "skipped a malformed cookie");
+                    errors.push(ParseError {
+                        fragment: cookie_str.trim().to_owned(),
+                        reason: ParseErrorReason::Cookie(error),
+                    });
+                }
+            }
+        }
+    }
+    (cookies, errors)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn errors() {
+        let headers = [
+            HeaderValue::from_static("a=1; =2; b; c=3;"),
+            HeaderValue::from_bytes(b"d=\xff").unwrap(),
+        ];
+        let (cookies, errors) = parse_headers(&headers);
+        let names: Vec<_> = cookies.iter().map(Cookie::name).collect();
+        assert_eq!(names, ["a", "c"]);
+        let errors: Vec<_> = errors.iter().map(|e| (e.fragment(), e.reason())).collect();
+        assert_eq!(
+            errors,
+            [
+                (
+                    "=2",
+                    ParseErrorReason::Cookie(cookie::ParseError::EmptyName)
+                ),
+                (
+                    "b",
+                    ParseErrorReason::Cookie(cookie::ParseError::MissingPair)
+                ),
+                ("d=\u{fffd}", ParseErrorReason::NonUtf8Header),
+            ]
+        );
+    }
+}
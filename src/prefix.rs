@@ -0,0 +1,158 @@
+//! Cookie name prefixes.
+//!
+//! Browsers reject cookies named with the `__Secure-` prefix unless they have the `Secure`
+//! attribute, and cookies named with the `__Host-` prefix unless they also have `Path=/` and no
+//! `Domain`. What happens to the outgoing cookies breaking the rules is configured by
+//! [`CookieManagerLayer::prefix_policy`](crate::CookieManagerLayer::prefix_policy).
+
+use cookie::Cookie;
+use std::borrow::Cow;
+
+/// The prefix of cookies only sent over secure connections.
+pub const SECURE_PREFIX: &str = "__Secure-";
+
+/// The prefix of cookies bound to the host and sent over secure connections.
+pub const HOST_PREFIX: &str = "__Host-";
+
+/// What to do with outgoing prefixed cookies breaking the prefix rules.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PrefixPolicy {
+    /// Send the cookie as is.
+    #[default]
+    Ignore,
+    /// Don't send the cookie.
+    Reject,
+    /// Set the attributes required by the prefix.
+    FixUp,
+    /// Send the cookie as is, emitting a `tracing` warning with the `tracing` feature. Without
+    /// the feature, it's the same as [`PrefixPolicy::Ignore`].
+    Log,
+}
+
+impl PrefixPolicy {
+    /// Applies the policy to the `cookie`. Returns `false` if the cookie shouldn't be sent.
+    pub(crate) fn enforce(self, cookie: &mut Cookie<'static>) -> bool {
+        if self == Self::Ignore {
+            return true;
+        }
+        let Some(violation) = violation(cookie) else {
+            return true;
+        };
+        match self {
+            Self::Ignore => true,
+            Self::Reject => false,
+            Self::FixUp => {
+                fix_up(cookie);
+                true
+            }
+            Self::Log => {
+                #[cfg(feature = "tracing")]
+                tracing::warn!(name = cookie.name(), "cookie {violation}");
+                #[cfg(not(feature = "tracing"))]
+                let _ = violation;
+                true
+            }
+        }
+    }
+}
+
+/// Creates a cookie named with the `__Host-` prefix and the required attributes.
+///
+/// # Example:
+/// ```
+/// let cookie = tower_cookies::prefix::host("id", "1");
+/// assert_eq!(cookie.to_string(), "__Host-id=1; Secure; Path=/");
+/// ```
+pub fn host(name: impl AsRef<str>, value: impl Into<Cow<'static, str>>) -> Cookie<'static> {
+    Cookie::build((format!("{HOST_PREFIX}{}", name.as_ref()), value))
+        .secure(true)
+        .path("/")
+        .build()
+}
+
+/// Creates a cookie named with the `__Secure-` prefix and the required attributes.
+///
+/// # Example:
+/// ```
+/// let cookie = tower_cookies::prefix::secure("id", "1");
+/// assert_eq!(cookie.to_string(), "__Secure-id=1; Secure");
+/// ```
+pub fn secure(name: impl AsRef<str>, value: impl Into<Cow<'static, str>>) -> Cookie<'static> {
+    Cookie::build((format!("{SECURE_PREFIX}{}", name.as_ref()), value))
+        .secure(true)
+        .build()
+}
+
+fn has_prefix(name: &str, prefix: &str) -> bool {
+    name.get(..prefix.len())
+        .is_some_and(|p| p.eq_ignore_ascii_case(prefix))
+}
+
+/// Returns the description of the broken prefix rule.
+fn violation(cookie: &Cookie<'_>) -> Option<&'static str> {
+    let name = cookie.name();
+    let host = has_prefix(name, HOST_PREFIX);
+    if !host && !has_prefix(name, SECURE_PREFIX) {
+        return None;
+    }
+    if cookie.secure() != Some(true) {
+        return Some("has a prefix requiring the `Secure` attribute");
+    }
+    if host && cookie.domain().is_some() {
+        return Some("has the `__Host-` prefix forbidding the `Domain` attribute");
+    }
+    if host && cookie.path() != Some("/") {
+        return Some("has the `__Host-` prefix requiring the `Path=/` attribute");
+    }
+    None
+}
+
+fn fix_up(cookie: &mut Cookie<'static>) {
+    cookie.set_secure(true);
+    if has_prefix(cookie.name(), HOST_PREFIX) {
+        cookie.unset_domain();
+        cookie.set_path("/");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn valid() {
+        for mut cookie in [host("a", "1"), secure("a", "1"), Cookie::new("a", "1")] {
+            let expected = cookie.clone();
+            assert!(PrefixPolicy::Reject.enforce(&mut cookie));
+            assert_eq!(cookie.to_string(), expected.to_string());
+        }
+    }
+
+    #[test]
+    fn reject() {
+        let mut cookie = Cookie::new("__Secure-a", "1");
+        assert!(!PrefixPolicy::Reject.enforce(&mut cookie));
+        let mut cookie = host("a", "1");
+        cookie.set_domain("example.com");
+        assert!(!PrefixPolicy::Reject.enforce(&mut cookie));
+        let mut cookie = Cookie::new("__host-a", "1");
+        assert!(!PrefixPolicy::Reject.enforce(&mut cookie));
+    }
+
+    #[test]
+    fn fix_up() {
+        let mut cookie = Cookie::build(("__Host-a", "1"))
+            .domain("example.com")
+            .path("/foo")
+            .build();
+        assert!(PrefixPolicy::FixUp.enforce(&mut cookie));
+        assert_eq!(cookie.to_string(), "__Host-a=1; Secure; Path=/");
+    }
+
+    #[test]
+    fn log() {
+        let mut cookie = Cookie::new("__Secure-a", "1");
+        assert!(PrefixPolicy::Log.enforce(&mut cookie));
+        assert_eq!(cookie.to_string(), "__Secure-a=1");
+    }
+}
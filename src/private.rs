@@ -1,5 +1,5 @@
-use crate::Cookies;
-use cookie::{Cookie, Key};
+use crate::{key::KeyRef, Cookies};
+use cookie::Cookie;
 
 /// A cookie jar that provides authenticated encryption for its cookies.
 ///
@@ -8,16 +8,17 @@ use cookie::{Cookie, Key};
 /// `PrivateCookies` are simultaneously assured confidentiality, integrity, and
 /// authenticity. In other words, clients cannot discover nor tamper with the
 /// contents of a cookie, nor can they fabricate cookie data.
+#[derive(Clone, Debug)]
 pub struct PrivateCookies<'a> {
     cookies: Cookies,
-    key: &'a Key,
+    key: KeyRef<'a>,
 }
 
 impl<'a> PrivateCookies<'a> {
     /// Creates an instance of `PrivateCookies` with parent `cookies` and key `key`.
     /// This method is typically called indirectly via the `private`
     /// method of [`Cookies`].
-    pub(crate) fn new(cookies: &Cookies, key: &'a Key) -> Self {
+    pub(crate) fn new(cookies: &Cookies, key: KeyRef<'a>) -> Self {
         Self {
             cookies: cookies.clone(),
             key,
@@ -30,7 +31,7 @@ impl<'a> PrivateCookies<'a> {
     pub fn add(&self, cookie: Cookie<'static>) {
         let mut inner = self.cookies.inner.lock();
         inner.changed = true;
-        inner.jar().private_mut(self.key).add(cookie);
+        inner.jar().private_mut(self.key.primary()).add(cookie);
     }
 
     /// Returns a reference to the `Cookie` inside this jar with the name `name`
@@ -39,7 +40,29 @@ impl<'a> PrivateCookies<'a> {
     /// fails to authenticate or decrypt, `None` is returned.
     pub fn get(&self, name: &str) -> Option<Cookie<'static>> {
         let mut inner = self.cookies.inner.lock();
-        inner.jar().private(self.key).get(name)
+        let jar = inner.jar();
+        if let Some(cookie) = jar.private(self.key.primary()).get(name) {
+            return Some(cookie);
+        }
+        let Some(cookie) = self
+            .key
+            .retired()
+            .iter()
+            .find_map(|key| jar.private(key).get(name))
+        else {
+            #[cfg(feature = "tracing")]
+            if jar.get(name).is_some() {
+                tracing::debug!(name, "failed to decrypt a private cookie");
+            }
+            return None;
+        };
+        #[cfg(feature = "tracing")]
+        tracing::debug!(name, "private cookie matched a retired key");
+        if self.key.reissue() {
+            jar.private_mut(self.key.primary()).add(cookie.clone());
+            inner.changed = true;
+        }
+        Some(cookie)
     }
 
     /// Removes the `cookie` from the parent jar.
@@ -51,12 +74,21 @@ impl<'a> PrivateCookies<'a> {
     pub fn remove(&self, cookie: Cookie<'static>) {
         self.cookies.remove(cookie);
     }
+
+    /// Removes the cookie named `name` from the parent jar, using the attributes registered by
+    /// [`CookieManagerLayer::register`](crate::CookieManagerLayer::register).
+    ///
+    /// See [`Cookies::remove_by_name`] for details.
+    pub fn remove_by_name(&self, name: &str) {
+        self.cookies.remove_by_name(name);
+    }
 }
 
 #[cfg(all(test, feature = "private"))]
 mod tests {
-    use crate::Cookies;
+    use crate::{service::Config, Cookies, KeyRing};
     use cookie::{Cookie, Key};
+    use std::sync::Arc;
 
     #[test]
     fn get_absent() {
@@ -113,4 +145,45 @@ mod tests {
         private.remove(cookie);
         assert!(private.get("foo").is_none());
     }
+
+    #[test]
+    fn owned() {
+        let key = Key::generate();
+        let cookies = Cookies::new(vec![]);
+        assert!(cookies.private_owned().is_none());
+        let cookies = cookies.with_config(Arc::new(Config {
+            keys: Some(Arc::new(KeyRing::new(key.clone()))),
+            ..Default::default()
+        }));
+        let cookie = Cookie::new("foo", "bar");
+        cookies.private_owned().unwrap().add(cookie.clone());
+        assert_eq!(cookies.private(&key).get("foo").unwrap(), cookie);
+    }
+
+    #[test]
+    fn retired_key() {
+        let old = Key::generate();
+        let cookies = Cookies::new(vec![]);
+        let cookie = Cookie::new("foo", "bar");
+        cookies.private(&old).add(cookie.clone());
+
+        let ring = KeyRing::new(Key::generate()).with_retired(old.clone());
+        assert_eq!(cookies.private_with_ring(&ring).get("foo").unwrap(), cookie);
+        assert_eq!(cookies.private(ring.primary()).get("foo"), None);
+    }
+
+    #[test]
+    fn reissue() {
+        let old = Key::generate();
+        let cookies = Cookies::new(vec![]);
+        let cookie = Cookie::new("foo", "bar");
+        cookies.private(&old).add(cookie.clone());
+
+        let ring = KeyRing::new(Key::generate())
+            .with_retired(old.clone())
+            .reissue(true);
+        assert_eq!(cookies.private_with_ring(&ring).get("foo").unwrap(), cookie);
+        assert_eq!(cookies.private(ring.primary()).get("foo").unwrap(), cookie);
+        assert_eq!(cookies.private(&old).get("foo"), None);
+    }
 }
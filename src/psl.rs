@@ -0,0 +1,111 @@
+//! Validation of cookie domains against the [Public Suffix List].
+//!
+//! Browsers reject cookies with a `Domain` attribute set to a public suffix, like `com` or
+//! `co.uk`, as such a cookie would be shared by unrelated sites. With the `psl` feature:
+//!
+//! - [`CookieManager`](crate::CookieManager) sends cookies with such a `Domain` without it, as
+//!   host-only cookies, like browsers treat them if the domain is the host itself, e.g.
+//!   `localhost`. Such cookies are reported to the hook set by
+//!   [`CookieManagerLayer::on_invalid_cookie`](crate::CookieManagerLayer::on_invalid_cookie).
+//! - [`ClientJar`](crate::client::ClientJar) rejects them like a browser does, unless the domain
+//!   is the host which set the cookie, in which case the cookie becomes host-only.
+//!
+//! The list is an embedded snapshot, with internationalized domains in punycode form.
+//!
+//! [Public Suffix List]: https://publicsuffix.org/
+
+use std::{collections::HashSet, sync::OnceLock};
+
+static LIST: &str = include_str!("../data/public_suffix_list.dat");
+
+#[derive(Debug, Default)]
+struct Rules {
+    /// Normal rules, e.g. `co.uk`
+    suffixes: HashSet<&'static str>,
+    /// Wildcard rules without the `*.` prefix, e.g. `ck` for `*.ck`
+    wildcards: HashSet<&'static str>,
+    /// Exception rules without the `!` prefix, e.g. `www.ck` for `!www.ck`
+    exceptions: HashSet<&'static str>,
+}
+
+fn rules() -> &'static Rules {
+    static RULES: OnceLock<Rules> = OnceLock::new();
+    RULES.get_or_init(|| {
+        let mut rules = Rules::default();
+        for line in LIST.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with("//") {
+                continue;
+            }
+            if let Some(rule) = line.strip_prefix('!') {
+                rules.exceptions.insert(rule);
+            } else if let Some(rule) = line.strip_prefix("*.") {
+                rules.wildcards.insert(rule);
+            } else {
+                rules.suffixes.insert(line);
+            }
+        }
+        rules
+    })
+}
+
+/// Returns `true` if the `domain` is a public suffix, e.g. `com` or `co.uk`.
+///
+/// A leading dot is ignored, and single-label domains are public suffixes as required by the
+/// implicit `*` rule of the list.
+///
+/// # Example:
+/// ```
+/// use tower_cookies::psl::is_public_suffix;
+///
+/// assert!(is_public_suffix("co.uk"));
+/// assert!(!is_public_suffix("example.co.uk"));
+/// ```
+pub fn is_public_suffix(domain: &str) -> bool {
+    let domain = domain
+        .trim_start_matches('.')
+        .trim_end_matches('.')
+        .to_ascii_lowercase();
+    if domain.is_empty() {
+        return true;
+    }
+    let rules = rules();
+    if rules.exceptions.contains(domain.as_str()) {
+        return false;
+    }
+    if rules.suffixes.contains(domain.as_str()) {
+        return true;
+    }
+    match domain.split_once('.') {
+        Some((_, parent)) => rules.wildcards.contains(parent),
+        None => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn suffixes() {
+        for domain in [
+            "com",
+            "co.uk",
+            ".CO.UK",
+            "github.io",
+            "anything.ck",
+            "xn--55qx5d.cn",
+        ] {
+            assert!(is_public_suffix(domain), "{domain}");
+        }
+        for domain in [
+            "example.com",
+            "example.co.uk",
+            "www.ck",
+            "a.b.ck",
+            "user.github.io",
+        ] {
+            assert!(!is_public_suffix(domain), "{domain}");
+        }
+    }
+}
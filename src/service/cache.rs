@@ -0,0 +1,160 @@
+use http::{header, HeaderMap, HeaderValue};
+
+/// The `Cache-Control` directive added to responses setting cookies, so shared caches don't
+/// serve them, with the cookies, to other users.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CacheControl {
+    /// `private`: only the browser may cache the response. Replaces `public`.
+    Private,
+    /// `no-store`: the response isn't cached at all. Removes `public`.
+    NoStore,
+}
+
+/// A response extension disabling the headers added by
+/// [`CookieManagerLayer::cache_control`](crate::CookieManagerLayer::cache_control) and
+/// [`CookieManagerLayer::vary_cookie`](crate::CookieManagerLayer::vary_cookie), e.g. for a
+/// response whose cookies are the same for every user.
+///
+/// # Example:
+/// ```
+/// use axum::{routing::get, Extension, Router};
+/// use tower_cookies::{
+///     service::{CacheControl, SkipCacheHeaders},
+///     Cookie, CookieManagerLayer, Cookies,
+/// };
+///
+/// async fn handler(cookies: Cookies) -> (Extension<SkipCacheHeaders>, &'static str) {
+///     cookies.add(Cookie::new("consent", "required"));
+///     (Extension(SkipCacheHeaders), "public page")
+/// }
+///
+/// let app: Router = Router::new()
+///     .route("/", get(handler))
+///     .layer(CookieManagerLayer::new().cache_control(CacheControl::Private));
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SkipCacheHeaders;
+
+impl CacheControl {
+    fn directive(self) -> &'static str {
+        match self {
+            Self::Private => "private",
+            Self::NoStore => "no-store",
+        }
+    }
+
+    /// Adds the directive to the `Cache-Control` header, keeping the other directives except
+    /// `public`. Nothing is changed if the header already has the directive or a stricter one.
+    pub(crate) fn apply(self, headers: &mut HeaderMap) {
+        let mut directives: Vec<String> = headers
+            .get_all(header::CACHE_CONTROL)
+            .iter()
+            .filter_map(|v| v.to_str().ok())
+            .flat_map(|v| v.split(','))
+            .map(|d| d.trim().to_owned())
+            .filter(|d| !d.is_empty())
+            .collect();
+        // `private` with field names, e.g. `private="Authorization"`, doesn't cover the cookies
+        let has = |directives: &[String], name: &str| {
+            directives.iter().any(|d| d.eq_ignore_ascii_case(name))
+        };
+        let satisfied = match self {
+            Self::Private => has(&directives, "private") || has(&directives, "no-store"),
+            Self::NoStore => has(&directives, "no-store"),
+        };
+        if satisfied {
+            return;
+        }
+        directives.retain(|d| !d.eq_ignore_ascii_case("public"));
+        directives.push(self.directive().to_owned());
+        if let Ok(value) = HeaderValue::from_str(&directives.join(", ")) {
+            headers.insert(header::CACHE_CONTROL, value);
+        }
+    }
+}
+
+/// Adds the headers configured by
+/// [`CookieManagerLayer::cache_control`](crate::CookieManagerLayer::cache_control) and
+/// [`CookieManagerLayer::vary_cookie`](crate::CookieManagerLayer::vary_cookie) to a response
+/// setting cookies.
+pub(crate) fn protect(headers: &mut HeaderMap, cache_control: Option<CacheControl>, vary: bool) {
+    if let Some(directive) = cache_control {
+        directive.apply(headers);
+    }
+    if vary {
+        vary_cookie(headers);
+    }
+}
+
+/// Adds `Cookie` to the `Vary` header, unless it's already there or the header is `*`.
+pub(crate) fn vary_cookie(headers: &mut HeaderMap) {
+    let varies = headers
+        .get_all(header::VARY)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .map(str::trim)
+        .any(|name| name == "*" || name.eq_ignore_ascii_case("cookie"));
+    if !varies {
+        headers.append(header::VARY, HeaderValue::from_static("Cookie"));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cache_control(policy: CacheControl, existing: &[&'static str]) -> String {
+        let mut headers = HeaderMap::new();
+        for value in existing {
+            headers.append(header::CACHE_CONTROL, HeaderValue::from_static(value));
+        }
+        policy.apply(&mut headers);
+        let values: Vec<_> = headers
+            .get_all(header::CACHE_CONTROL)
+            .iter()
+            .map(|v| v.to_str().unwrap())
+            .collect();
+        values.join(" | ")
+    }
+
+    #[test]
+    fn tightens_cache_control() {
+        use CacheControl::{NoStore, Private};
+
+        assert_eq!(cache_control(Private, &[]), "private");
+        assert_eq!(
+            cache_control(Private, &["max-age=60"]),
+            "max-age=60, private"
+        );
+        assert_eq!(
+            cache_control(Private, &["Public", "max-age=60"]),
+            "max-age=60, private"
+        );
+        assert_eq!(cache_control(Private, &["no-store"]), "no-store");
+        assert_eq!(
+            cache_control(Private, &["private=\"x\", max-age=60"]),
+            "private=\"x\", max-age=60, private"
+        );
+        assert_eq!(cache_control(NoStore, &["private"]), "private, no-store");
+        assert_eq!(
+            cache_control(NoStore, &["public, no-store"]),
+            "public, no-store"
+        );
+    }
+
+    #[test]
+    fn vary() {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::VARY, HeaderValue::from_static("Accept"));
+        vary_cookie(&mut headers);
+        vary_cookie(&mut headers);
+        let values: Vec<_> = headers.get_all(header::VARY).iter().collect();
+        assert_eq!(values, ["Accept", "Cookie"]);
+
+        let mut headers = HeaderMap::new();
+        headers.insert(header::VARY, HeaderValue::from_static("*"));
+        vary_cookie(&mut headers);
+        assert_eq!(headers.get_all(header::VARY).iter().count(), 1);
+    }
+}
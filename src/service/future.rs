@@ -1,22 +1,36 @@
 //! [`Future`] types.
 
-use crate::Cookies;
+use super::{cache, Config, ConflictPolicy, SkipCacheHeaders, StashedCookies};
+#[cfg(feature = "session")]
+use crate::session::{Persist, Session};
+use crate::{chunk, validate, CookieChanges, Cookies};
+use cookie::{
+    time::{Duration, OffsetDateTime},
+    Cookie,
+};
 use futures_util::ready;
-use http::{header, HeaderValue, Response};
+use http::{header, HeaderMap, HeaderValue, Response};
 use pin_project_lite::pin_project;
 use std::{
+    collections::HashSet,
+    fmt,
     future::Future,
     pin::Pin,
+    sync::Arc,
     task::{Context, Poll},
 };
 
 pin_project! {
     /// Response future for [`CookieManager`].
-    #[derive(Debug)]
-    pub struct ResponseFuture<F> {
+    pub struct ResponseFuture<F>
+    where
+        F: Future,
+    {
         #[pin]
         pub(crate) future: F,
-        pub(crate) cookies: Cookies,
+        // The output of the inner service while the session is being saved
+        pub(crate) output: Option<F::Output>,
+        pub(crate) state: ResponseState,
     }
 }
 
@@ -28,21 +42,231 @@ where
 
     fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
         let this = self.project();
-        let mut res = ready!(this.future.poll(cx)?);
+        #[cfg(feature = "tracing")]
+        let _enter = this.state.span.enter();
+        if this.output.is_none() {
+            let output = ready!(this.future.poll(cx));
+            #[cfg(feature = "session")]
+            if output.is_ok() || this.state.stash.is_some() {
+                this.state.persisting = this.state.session.as_ref().and_then(Session::persist);
+            }
+            *this.output = Some(output);
+        }
+        #[cfg(feature = "session")]
+        if let Some(persisting) = &mut this.state.persisting {
+            ready!(Pin::new(persisting).poll(cx));
+            this.state.persisting = None;
+        }
+        let mut res = match this.output.take().expect("polled after completion") {
+            Ok(res) => res,
+            Err(err) => {
+                if let Some(stash) = &this.state.stash {
+                    stash.extend(this.state.set_cookie_values(), &this.state.config);
+                }
+                return Poll::Ready(Err(err));
+            }
+        };
+        if let Some(changes) = res.extensions_mut().remove::<CookieChanges>() {
+            changes.apply(&this.state.cookies);
+        }
+        let values = this.state.set_cookie_values();
+        this.state.merge(res.headers_mut(), values);
+        // Includes the `Set-Cookie` headers set by the inner service
+        let skip_cache_headers = res.extensions_mut().remove::<SkipCacheHeaders>().is_some();
+        if res.headers().contains_key(header::SET_COOKIE) && !skip_cache_headers {
+            let config = &this.state.config;
+            cache::protect(res.headers_mut(), config.cache_control, config.vary_cookie);
+        }
+        res.extensions_mut().insert(this.state.cookies.clone());
+        Poll::Ready(Ok(res))
+    }
+}
 
-        let mut cookies = this.cookies.inner.lock();
+impl<F> fmt::Debug for ResponseFuture<F>
+where
+    F: Future + fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ResponseFuture")
+            .field("future", &self.future)
+            .field("state", &self.state)
+            .finish()
+    }
+}
+
+/// The request state needed to finalize the response.
+#[derive(Debug)]
+pub(crate) struct ResponseState {
+    pub(crate) cookies: Cookies,
+    pub(crate) config: Arc<Config>,
+    #[cfg(feature = "session")]
+    pub(crate) session: Option<Session>,
+    /// Saves the server-side session before the response is finalized
+    #[cfg(feature = "session")]
+    pub(crate) persisting: Option<Persist>,
+    /// Where to put the changes if the inner service fails
+    pub(crate) stash: Option<StashedCookies>,
+    #[cfg(feature = "tracing")]
+    pub(crate) span: tracing::Span,
+}
+
+impl ResponseState {
+    /// Returns the `Set-Cookie` header values for the changes of the cookies.
+    fn set_cookie_values(&self) -> Vec<HeaderValue> {
+        let mut values = Vec::new();
+        let mut cookies = self.cookies.inner.lock();
         if cookies.changed {
-            let values: Vec<_> = cookies
-                .jar()
-                .delta()
-                .filter_map(|c| HeaderValue::from_str(&c.to_string()).ok())
-                .collect();
-            let headers = res.headers_mut();
+            let delta: Vec<_> = cookies.jar().delta().cloned().collect();
+            for mut cookie in delta {
+                self.config.apply_registered(&mut cookie);
+                self.config.defaults.apply(&mut cookie);
+                if !self.config.prefix_policy.enforce(&mut cookie) {
+                    #[cfg(feature = "tracing")]
+                    tracing::debug!(
+                        name = cookie.name(),
+                        "dropped a cookie breaking prefix rules"
+                    );
+                    continue;
+                }
+                // Browsers treat such a cookie as host-only if the domain is the host, and reject
+                // it otherwise, e.g. `Domain=localhost` is fine on `localhost`
+                #[cfg(feature = "psl")]
+                if cookie.domain().is_some_and(crate::psl::is_public_suffix) {
+                    let error = validate::public_suffix_domain(&cookie);
+                    #[cfg(feature = "tracing")]
+                    tracing::warn!(%error, domain = cookie.domain(), "unset a cookie domain");
+                    if let Some(hook) = &self.config.on_invalid_cookie {
+                        (hook.0)(&error);
+                    }
+                    cookie.unset_domain();
+                }
+                #[cfg(feature = "tracing")]
+                tracing::debug!(
+                    name = cookie.name(),
+                    value = self.traced_value(cookie.value()),
+                    removal = cookie.max_age() == Some(cookie::time::Duration::ZERO),
+                    "sending cookie"
+                );
+                let chunks = match self.config.chunk_size {
+                    Some(max_size) => {
+                        let original = cookies.chunks.get(cookie.name()).copied();
+                        chunk::split(cookie, max_size, original)
+                    }
+                    None => vec![cookie],
+                };
+                for cookie in chunks {
+                    if let Ok(value) = HeaderValue::from_str(&cookie.to_string()) {
+                        values.push(value);
+                        continue;
+                    }
+                    let error = validate::invalid_header(&cookie);
+                    #[cfg(feature = "tracing")]
+                    tracing::warn!(%error, "dropped an invalid cookie");
+                    if let Some(hook) = &self.config.on_invalid_cookie {
+                        (hook.0)(&error);
+                    }
+                }
+            }
+        }
+        values
+    }
+
+    /// Appends the `values` to the `Set-Cookie` headers of the response, resolving the conflicts
+    /// with the ones set by the inner service, and adds the cookies set by the inner service to
+    /// the jar, so outer layers can read them.
+    fn merge(&self, headers: &mut HeaderMap, mut values: Vec<HeaderValue>) {
+        let mut existing: Vec<_> = headers
+            .get_all(header::SET_COOKIE)
+            .iter()
+            .cloned()
+            .collect();
+        if existing.is_empty() {
             for value in values {
                 headers.append(header::SET_COOKIE, value);
             }
+            return;
+        }
+        let names = |values: &[HeaderValue]| -> HashSet<String> {
+            values
+                .iter()
+                .filter_map(cookie_name)
+                .map(str::to_owned)
+                .collect()
+        };
+        #[cfg(feature = "tracing")]
+        let total = existing.len() + values.len();
+        match self.config.conflict_policy {
+            ConflictPolicy::KeepBoth => (),
+            ConflictPolicy::JarWins => {
+                let jar_names = names(&values);
+                existing.retain(|v| cookie_name(v).map_or(true, |n| !jar_names.contains(n)));
+            }
+            ConflictPolicy::HandlerWins => {
+                let handler_names = names(&existing);
+                values.retain(|v| cookie_name(v).map_or(true, |n| !handler_names.contains(n)));
+            }
+        }
+        #[cfg(feature = "tracing")]
+        if existing.len() + values.len() < total {
+            tracing::debug!(
+                dropped = total - existing.len() - values.len(),
+                policy = ?self.config.conflict_policy,
+                "dropped conflicting Set-Cookie headers"
+            );
         }
 
-        Poll::Ready(Ok(res))
+        // The later header wins in browsers, so the jar values shadow the existing ones
+        let jar_names = names(&values);
+        let mut cookies = self.cookies.inner.lock();
+        let jar = cookies.jar();
+        for value in &existing {
+            let Some(cookie) = value
+                .to_str()
+                .ok()
+                .and_then(|v| Cookie::parse(v.to_owned()).ok())
+            else {
+                continue;
+            };
+            if jar_names.contains(cookie.name()) {
+                continue;
+            }
+            if is_removal(&cookie) {
+                jar.remove(cookie);
+            } else {
+                jar.add(cookie);
+            }
+        }
+
+        headers.remove(header::SET_COOKIE);
+        for value in existing.into_iter().chain(values) {
+            headers.append(header::SET_COOKIE, value);
+        }
+    }
+
+    /// Returns the `value` to trace, which is redacted unless
+    /// [`CookieManagerLayer::trace_values`](crate::CookieManagerLayer::trace_values) is enabled.
+    #[cfg(feature = "tracing")]
+    fn traced_value<'a>(&self, value: &'a str) -> &'a str {
+        if self.config.trace_values {
+            value
+        } else {
+            "[redacted]"
+        }
     }
 }
+
+/// Returns the name of the cookie of a `Set-Cookie` header value.
+fn cookie_name(value: &HeaderValue) -> Option<&str> {
+    let (name, _) = value.to_str().ok()?.split_once('=')?;
+    Some(name.trim())
+}
+
+/// Returns `true` if the `cookie` tells the client to remove it.
+pub(crate) fn is_removal(cookie: &Cookie<'_>) -> bool {
+    cookie
+        .max_age()
+        .is_some_and(|max_age| max_age <= Duration::ZERO)
+        || cookie
+            .expires_datetime()
+            .is_some_and(|expires| expires <= OffsetDateTime::now_utc())
+}
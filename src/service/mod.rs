@@ -1,24 +1,193 @@
 //! Middleware to use [`Cookies`].
 
-use self::future::ResponseFuture;
-use crate::Cookies;
+use self::future::{ResponseFuture, ResponseState};
+#[cfg(feature = "session")]
+use crate::session::{Session, SessionConfig};
+#[cfg(any(feature = "signed", feature = "private"))]
+use crate::KeyRing;
+use crate::{prefix::PrefixPolicy, Cookies, InvalidCookie};
+#[cfg(any(feature = "signed", feature = "private"))]
+use cookie::Key;
+use cookie::{Cookie, SameSite};
 use http::{header, Request, Response};
-use std::task::{Context, Poll};
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    fmt,
+    sync::Arc,
+    task::{Context, Poll},
+};
 use tower_layer::Layer;
 use tower_service::Service;
 
+pub use self::cache::{CacheControl, SkipCacheHeaders};
+pub use self::reject::{
+    RejectMalformedCookies, RejectMalformedCookiesFuture, RejectMalformedCookiesLayer,
+};
+pub use self::stash::{
+    ApplyStashedCookies, ApplyStashedCookiesFuture, ApplyStashedCookiesLayer, ErrorPolicy,
+    StashedCookies,
+};
+
+mod cache;
 pub mod future;
+mod reject;
+mod stash;
 
 /// Middleware to use [`Cookies`].
 #[derive(Clone, Debug)]
 pub struct CookieManager<S> {
     inner: S,
+    config: Arc<Config>,
 }
 
 impl<S> CookieManager<S> {
     /// Create a new cookie manager.
     pub fn new(inner: S) -> Self {
-        Self { inner }
+        Self {
+            inner,
+            config: Default::default(),
+        }
+    }
+
+    /// Sets the default `Secure` attribute of the cookies sent to the client.
+    ///
+    /// See [`CookieManagerLayer::secure`] for details.
+    pub fn secure(mut self, value: bool) -> Self {
+        Arc::make_mut(&mut self.config).defaults.secure = Some(value);
+        self
+    }
+
+    /// Sets the default `HttpOnly` attribute of the cookies sent to the client.
+    ///
+    /// See [`CookieManagerLayer::http_only`] for details.
+    pub fn http_only(mut self, value: bool) -> Self {
+        Arc::make_mut(&mut self.config).defaults.http_only = Some(value);
+        self
+    }
+
+    /// Sets the default `SameSite` attribute of the cookies sent to the client.
+    ///
+    /// See [`CookieManagerLayer::same_site`] for details.
+    pub fn same_site(mut self, value: SameSite) -> Self {
+        Arc::make_mut(&mut self.config).defaults.same_site = Some(value);
+        self
+    }
+
+    /// Sets the default `Path` attribute of the cookies sent to the client.
+    ///
+    /// See [`CookieManagerLayer::path`] for details.
+    pub fn path(mut self, value: impl Into<Cow<'static, str>>) -> Self {
+        Arc::make_mut(&mut self.config).defaults.path = Some(value.into());
+        self
+    }
+
+    /// Sets the handling of cookies breaking the rules of the `__Host-` and `__Secure-` prefixes.
+    ///
+    /// See [`CookieManagerLayer::prefix_policy`] for details.
+    pub fn prefix_policy(mut self, policy: PrefixPolicy) -> Self {
+        Arc::make_mut(&mut self.config).prefix_policy = policy;
+        self
+    }
+
+    /// Enables splitting of cookie values longer than `max_size` bytes into chunks.
+    ///
+    /// See [`CookieManagerLayer::chunk_size`] for details.
+    pub fn chunk_size(mut self, max_size: usize) -> Self {
+        Arc::make_mut(&mut self.config).chunk_size = Some(max_size.max(1));
+        self
+    }
+
+    /// Sets a hook called for the cookies which can't be sent to the client.
+    ///
+    /// See [`CookieManagerLayer::on_invalid_cookie`] for details.
+    pub fn on_invalid_cookie<F>(mut self, hook: F) -> Self
+    where
+        F: Fn(&InvalidCookie) + Send + Sync + 'static,
+    {
+        Arc::make_mut(&mut self.config).on_invalid_cookie = Some(InvalidCookieHook(Arc::new(hook)));
+        self
+    }
+
+    /// Registers a cookie with its attributes.
+    ///
+    /// See [`CookieManagerLayer::register`] for details.
+    pub fn register(mut self, cookie: impl Into<Cookie<'static>>) -> Self {
+        let cookie = cookie.into();
+        Arc::make_mut(&mut self.config)
+            .registry
+            .insert(cookie.name().to_owned(), cookie);
+        self
+    }
+
+    /// Sets what happens to the cookie changes when the inner service returns an error.
+    ///
+    /// See [`CookieManagerLayer::error_policy`] for details.
+    pub fn error_policy(mut self, policy: ErrorPolicy) -> Self {
+        Arc::make_mut(&mut self.config).error_policy = policy;
+        self
+    }
+
+    /// Sets how the `Set-Cookie` headers of the inner service are merged with the cookie changes.
+    ///
+    /// See [`CookieManagerLayer::conflict_policy`] for details.
+    pub fn conflict_policy(mut self, policy: ConflictPolicy) -> Self {
+        Arc::make_mut(&mut self.config).conflict_policy = policy;
+        self
+    }
+
+    /// Sets the `Cache-Control` directive added to the responses setting cookies.
+    ///
+    /// See [`CookieManagerLayer::cache_control`] for details.
+    pub fn cache_control(mut self, directive: CacheControl) -> Self {
+        Arc::make_mut(&mut self.config).cache_control = Some(directive);
+        self
+    }
+
+    /// Adds `Vary: Cookie` to the responses setting cookies.
+    ///
+    /// See [`CookieManagerLayer::vary_cookie`] for details.
+    pub fn vary_cookie(mut self, value: bool) -> Self {
+        Arc::make_mut(&mut self.config).vary_cookie = value;
+        self
+    }
+
+    /// Includes the cookie values in the `tracing` events instead of redacting them.
+    ///
+    /// See [`CookieManagerLayer::trace_values`] for details.
+    #[cfg(feature = "tracing")]
+    pub fn trace_values(mut self, value: bool) -> Self {
+        Arc::make_mut(&mut self.config).trace_values = value;
+        self
+    }
+
+    /// Sets the key used by the [`Cookies::signed_owned`] and [`Cookies::private_owned`] jars.
+    ///
+    /// See [`CookieManagerLayer::with_key`] for details.
+    #[cfg(any(feature = "signed", feature = "private"))]
+    pub fn with_key(self, key: Key) -> Self {
+        self.with_key_ring(KeyRing::new(key))
+    }
+
+    /// Sets the key ring used by the [`Cookies::signed_owned`] and [`Cookies::private_owned`]
+    /// jars.
+    ///
+    /// See [`CookieManagerLayer::with_key_ring`] for details.
+    #[cfg(any(feature = "signed", feature = "private"))]
+    pub fn with_key_ring(mut self, ring: KeyRing) -> Self {
+        Arc::make_mut(&mut self.config).keys = Some(Arc::new(ring));
+        self
+    }
+
+    /// Enables sessions.
+    ///
+    /// Sessions require a key set by [`CookieManager::with_key`] or
+    /// [`CookieManager::with_key_ring`], before or after this method. Without it, the service
+    /// panics on the first request. See [`CookieManagerLayer::with_session`] for details.
+    #[cfg(feature = "session")]
+    pub fn with_session(mut self, config: SessionConfig) -> Self {
+        Arc::make_mut(&mut self.config).session = Some(Arc::new(config));
+        self
     }
 }
 
@@ -42,26 +211,291 @@ where
             .iter()
             .cloned()
             .collect();
-        let cookies = Cookies::new(value);
+        #[cfg(feature = "tracing")]
+        let span = tracing::debug_span!("cookies", method = %req.method(), path = req.uri().path());
+        let cookies = Cookies::new(value).with_config(self.config.clone());
         req.extensions_mut().insert(cookies.clone());
 
+        #[cfg(feature = "session")]
+        self.config.assert_session_key();
+        #[cfg(feature = "session")]
+        let session = self.config.session.clone().map(|config| {
+            let session = Session::new(cookies.clone(), config);
+            req.extensions_mut().insert(session.clone());
+            session
+        });
+
+        let stash = match self.config.error_policy {
+            ErrorPolicy::Drop => None,
+            ErrorPolicy::Stash => req.extensions().get::<StashedCookies>().cloned(),
+        };
+
         ResponseFuture {
             future: self.inner.call(req),
-            cookies,
+            output: None,
+            state: ResponseState {
+                cookies,
+                config: self.config.clone(),
+                #[cfg(feature = "session")]
+                session,
+                #[cfg(feature = "session")]
+                persisting: None,
+                stash,
+                #[cfg(feature = "tracing")]
+                span,
+            },
         }
     }
 }
 
 /// Layer to apply [`CookieManager`] middleware.
+///
+/// The layer can be configured with default attributes which are applied to every cookie sent to
+/// the client unless the cookie sets the attribute explicitly:
+///
+/// ```
+/// use tower_cookies::{cookie::SameSite, CookieManagerLayer};
+///
+/// let layer = CookieManagerLayer::new()
+///     .secure(true)
+///     .http_only(true)
+///     .same_site(SameSite::Lax)
+///     .path("/");
+/// ```
 #[derive(Clone, Debug, Default)]
 pub struct CookieManagerLayer {
-    _priv: (),
+    config: Arc<Config>,
 }
 
 impl CookieManagerLayer {
     /// Create a new cookie manager layer.
     pub fn new() -> Self {
-        Self { _priv: () }
+        Self::default()
+    }
+
+    /// Sets the default `Secure` attribute of the cookies sent to the client.
+    ///
+    /// Cookies having the attribute set explicitly, e.g. by [`Cookie::set_secure`], are left
+    /// untouched.
+    pub fn secure(mut self, value: bool) -> Self {
+        Arc::make_mut(&mut self.config).defaults.secure = Some(value);
+        self
+    }
+
+    /// Sets the default `HttpOnly` attribute of the cookies sent to the client.
+    ///
+    /// Cookies having the attribute set explicitly, e.g. by [`Cookie::set_http_only`], are left
+    /// untouched.
+    pub fn http_only(mut self, value: bool) -> Self {
+        Arc::make_mut(&mut self.config).defaults.http_only = Some(value);
+        self
+    }
+
+    /// Sets the default `SameSite` attribute of the cookies sent to the client.
+    ///
+    /// Cookies having the attribute set explicitly, e.g. by [`Cookie::set_same_site`], are left
+    /// untouched.
+    pub fn same_site(mut self, value: SameSite) -> Self {
+        Arc::make_mut(&mut self.config).defaults.same_site = Some(value);
+        self
+    }
+
+    /// Sets the default `Path` attribute of the cookies sent to the client.
+    ///
+    /// Cookies having the attribute set explicitly, e.g. by [`Cookie::set_path`], are left
+    /// untouched. The default is also applied to removal cookies, so a cookie added with the
+    /// default path can be removed without specifying it again.
+    pub fn path(mut self, value: impl Into<Cow<'static, str>>) -> Self {
+        Arc::make_mut(&mut self.config).defaults.path = Some(value.into());
+        self
+    }
+
+    /// Sets the handling of cookies breaking the rules of the `__Host-` and `__Secure-` prefixes,
+    /// which browsers silently drop. By default, such cookies are sent as is.
+    ///
+    /// The policy is applied after the default attributes, so e.g. `__Secure-` cookies are valid
+    /// when [`CookieManagerLayer::secure`] is enabled.
+    ///
+    /// ```
+    /// use tower_cookies::{prefix::PrefixPolicy, CookieManagerLayer};
+    ///
+    /// let layer = CookieManagerLayer::new().prefix_policy(PrefixPolicy::FixUp);
+    /// ```
+    pub fn prefix_policy(mut self, policy: PrefixPolicy) -> Self {
+        Arc::make_mut(&mut self.config).prefix_policy = policy;
+        self
+    }
+
+    /// Enables splitting of cookie values longer than `max_size` bytes into chunks.
+    ///
+    /// Browsers drop cookies larger than about 4096 bytes, which is easy to exceed with private
+    /// cookies or sessions. With chunking enabled, a long value of the cookie `name` is sent as
+    /// `name.0`, `name.1`, ... cookies, which are joined back into `name` when the request cookies
+    /// are parsed, including the signed and private jars. Stale chunks are removed when the value
+    /// shrinks or the cookie is removed.
+    ///
+    /// ```
+    /// use tower_cookies::CookieManagerLayer;
+    ///
+    /// let layer = CookieManagerLayer::new().chunk_size(3800);
+    /// ```
+    pub fn chunk_size(mut self, max_size: usize) -> Self {
+        Arc::make_mut(&mut self.config).chunk_size = Some(max_size.max(1));
+        self
+    }
+
+    /// Sets a hook called for the cookies which can't be sent to the client, e.g. because of
+    /// control characters in the value. Such cookies are dropped, so the hook helps to find out
+    /// why a cookie is missing. Use [`Cookies::try_add`] to catch them when they're added.
+    ///
+    /// With the `psl` feature, the hook is also called for the cookies with a public suffix
+    /// `Domain`, which are sent host-only.
+    ///
+    /// ```
+    /// use tower_cookies::CookieManagerLayer;
+    ///
+    /// let layer = CookieManagerLayer::new().on_invalid_cookie(|error| eprintln!("{error}"));
+    /// ```
+    pub fn on_invalid_cookie<F>(mut self, hook: F) -> Self
+    where
+        F: Fn(&InvalidCookie) + Send + Sync + 'static,
+    {
+        Arc::make_mut(&mut self.config).on_invalid_cookie = Some(InvalidCookieHook(Arc::new(hook)));
+        self
+    }
+
+    /// Registers a cookie with its attributes, e.g. `Path` and `Domain`.
+    ///
+    /// The registered attributes are applied to the cookies with the same name sent to the
+    /// client, unless the cookie sets them explicitly, and they're used by
+    /// [`Cookies::remove_by_name`] to build the removal cookie matching the one set before.
+    /// Registered attributes take precedence over the defaults set by e.g.
+    /// [`CookieManagerLayer::path`].
+    ///
+    /// ```
+    /// use tower_cookies::{Cookie, CookieManagerLayer};
+    ///
+    /// let layer = CookieManagerLayer::new().register(
+    ///     Cookie::build("session")
+    ///         .path("/app")
+    ///         .domain("example.com")
+    ///         .http_only(true),
+    /// );
+    /// ```
+    pub fn register(mut self, cookie: impl Into<Cookie<'static>>) -> Self {
+        let cookie = cookie.into();
+        Arc::make_mut(&mut self.config)
+            .registry
+            .insert(cookie.name().to_owned(), cookie);
+        self
+    }
+
+    /// Sets what happens to the cookie changes when the inner service returns an error.
+    ///
+    /// An error has no response to carry the `Set-Cookie` headers, so by default the changes,
+    /// including the session ones, are dropped. With [`ErrorPolicy::Stash`], they're kept in
+    /// [`StashedCookies`] and applied to the error response by [`ApplyStashedCookiesLayer`], which
+    /// should wrap the layer converting errors into responses.
+    pub fn error_policy(mut self, policy: ErrorPolicy) -> Self {
+        Arc::make_mut(&mut self.config).error_policy = policy;
+        self
+    }
+
+    /// Sets how the `Set-Cookie` headers of the inner service are merged with the cookie changes.
+    ///
+    /// A handler or an inner service may set a `Set-Cookie` header directly for a cookie also
+    /// changed in [`Cookies`]. By default both headers are sent, see [`ConflictPolicy`] for the
+    /// alternatives.
+    ///
+    /// Either way, the [`Cookies`] of the request are put into the response extensions, so outer
+    /// layers can read the cookies set by the response, including the ones set by the headers.
+    ///
+    /// # Example:
+    /// ```
+    /// use axum::{http::header, routing::get, Router};
+    /// use tower_cookies::{service::ConflictPolicy, Cookie, CookieManagerLayer, Cookies};
+    ///
+    /// async fn handler(cookies: Cookies) -> [(header::HeaderName, &'static str); 1] {
+    ///     cookies.add(Cookie::new("theme", "dark"));
+    ///     // Wins over the change above
+    ///     [(header::SET_COOKIE, "theme=light")]
+    /// }
+    ///
+    /// let app: Router = Router::new()
+    ///     .route("/", get(handler))
+    ///     .layer(CookieManagerLayer::new().conflict_policy(ConflictPolicy::HandlerWins));
+    /// ```
+    pub fn conflict_policy(mut self, policy: ConflictPolicy) -> Self {
+        Arc::make_mut(&mut self.config).conflict_policy = policy;
+        self
+    }
+
+    /// Sets the `Cache-Control` directive added to the responses setting cookies.
+    ///
+    /// A shared cache, e.g. a CDN, storing such a response would send its cookies, like a session
+    /// one, to other users. With the option, the directive is added to the `Cache-Control` header
+    /// of every response with `Set-Cookie` headers, including the ones set by the inner service,
+    /// unless the header already has it or a stricter one. `public` is removed.
+    ///
+    /// Responses with the [`SkipCacheHeaders`] extension are left untouched.
+    ///
+    /// # Example:
+    /// ```
+    /// use tower_cookies::{service::CacheControl, CookieManagerLayer};
+    ///
+    /// let layer = CookieManagerLayer::new()
+    ///     .cache_control(CacheControl::Private)
+    ///     .vary_cookie(true);
+    /// ```
+    pub fn cache_control(mut self, directive: CacheControl) -> Self {
+        Arc::make_mut(&mut self.config).cache_control = Some(directive);
+        self
+    }
+
+    /// Adds `Vary: Cookie` to the responses with `Set-Cookie` headers, so caches don't serve a
+    /// response to requests with different cookies.
+    ///
+    /// Responses with the [`SkipCacheHeaders`] extension are left untouched.
+    pub fn vary_cookie(mut self, value: bool) -> Self {
+        Arc::make_mut(&mut self.config).vary_cookie = value;
+        self
+    }
+
+    /// Includes the cookie values in the `tracing` events instead of redacting them.
+    ///
+    /// Cookie values often carry credentials, so they're redacted by default. Enable it only
+    /// for debugging.
+    #[cfg(feature = "tracing")]
+    pub fn trace_values(mut self, value: bool) -> Self {
+        Arc::make_mut(&mut self.config).trace_values = value;
+        self
+    }
+
+    /// Sets the key used by the [`Cookies::signed_owned`] and [`Cookies::private_owned`] jars.
+    ///
+    /// Storing the key inside the layer saves passing it through the application state to every
+    /// handler working with signed or private cookies.
+    #[cfg(any(feature = "signed", feature = "private"))]
+    pub fn with_key(self, key: Key) -> Self {
+        self.with_key_ring(KeyRing::new(key))
+    }
+
+    /// Sets the key ring used by the [`Cookies::signed_owned`] and [`Cookies::private_owned`]
+    /// jars, allowing to rotate the key without invalidating existing cookies.
+    #[cfg(any(feature = "signed", feature = "private"))]
+    pub fn with_key_ring(mut self, ring: KeyRing) -> Self {
+        Arc::make_mut(&mut self.config).keys = Some(Arc::new(ring));
+        self
+    }
+
+    /// Enables sessions, making [`Session`] available in the request extensions.
+    ///
+    /// Sessions are encrypted by the key set by [`CookieManagerLayer::with_key`], so the layer
+    /// panics on creating the service if the key isn't set. See [`crate::session`] for details.
+    #[cfg(feature = "session")]
+    pub fn with_session(mut self, config: SessionConfig) -> Self {
+        Arc::make_mut(&mut self.config).session = Some(Arc::new(config));
+        self
     }
 }
 
@@ -69,6 +503,125 @@ impl<S> Layer<S> for CookieManagerLayer {
     type Service = CookieManager<S>;
 
     fn layer(&self, inner: S) -> Self::Service {
-        CookieManager { inner }
+        #[cfg(feature = "session")]
+        self.config.assert_session_key();
+        CookieManager {
+            inner,
+            config: self.config.clone(),
+        }
+    }
+}
+
+/// What to do when the inner service sets a `Set-Cookie` header for a cookie changed in
+/// [`Cookies`].
+///
+/// Cookies are matched by name.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ConflictPolicy {
+    /// Send both headers, the one of the inner service first, so browsers keep the [`Cookies`]
+    /// one.
+    #[default]
+    KeepBoth,
+    /// Drop the header of the inner service.
+    JarWins,
+    /// Drop the change made in [`Cookies`].
+    HandlerWins,
+}
+
+/// Configuration shared by [`CookieManagerLayer`], [`CookieManager`] and its futures.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Config {
+    pub(crate) defaults: Defaults,
+    pub(crate) prefix_policy: PrefixPolicy,
+    pub(crate) chunk_size: Option<usize>,
+    pub(crate) error_policy: ErrorPolicy,
+    pub(crate) conflict_policy: ConflictPolicy,
+    pub(crate) cache_control: Option<CacheControl>,
+    pub(crate) vary_cookie: bool,
+    pub(crate) on_invalid_cookie: Option<InvalidCookieHook>,
+    /// Cookies registered by name with their attributes
+    pub(crate) registry: HashMap<String, Cookie<'static>>,
+    #[cfg(feature = "tracing")]
+    pub(crate) trace_values: bool,
+    #[cfg(any(feature = "signed", feature = "private"))]
+    pub(crate) keys: Option<Arc<KeyRing>>,
+    #[cfg(feature = "session")]
+    pub(crate) session: Option<Arc<SessionConfig>>,
+}
+
+impl Config {
+    /// Sets the attributes of the cookie registered under the name of the `cookie` which aren't
+    /// explicitly set by it.
+    pub(crate) fn apply_registered(&self, cookie: &mut Cookie<'static>) {
+        let Some(registered) = self.registry.get(cookie.name()) else {
+            return;
+        };
+        if cookie.path().is_none() {
+            if let Some(path) = registered.path() {
+                cookie.set_path(path.to_owned());
+            }
+        }
+        if cookie.domain().is_none() {
+            if let Some(domain) = registered.domain() {
+                cookie.set_domain(domain.to_owned());
+            }
+        }
+        if cookie.secure().is_none() {
+            cookie.set_secure(registered.secure());
+        }
+        if cookie.http_only().is_none() {
+            cookie.set_http_only(registered.http_only());
+        }
+        if cookie.same_site().is_none() {
+            cookie.set_same_site(registered.same_site());
+        }
+    }
+
+    /// Sessions are encrypted by the key, so they can't be enabled without it.
+    #[cfg(feature = "session")]
+    fn assert_session_key(&self) {
+        assert!(
+            self.session.is_none() || self.keys.is_some(),
+            "sessions require a key set by `with_key`"
+        );
+    }
+}
+
+/// A hook set by [`CookieManagerLayer::on_invalid_cookie`].
+#[derive(Clone)]
+pub(crate) struct InvalidCookieHook(pub(crate) Arc<dyn Fn(&InvalidCookie) + Send + Sync>);
+
+impl fmt::Debug for InvalidCookieHook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("InvalidCookieHook")
+    }
+}
+
+/// Default attributes of the outgoing cookies.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Defaults {
+    secure: Option<bool>,
+    http_only: Option<bool>,
+    same_site: Option<SameSite>,
+    path: Option<Cow<'static, str>>,
+}
+
+impl Defaults {
+    /// Sets the attributes which aren't explicitly set by the `cookie`.
+    pub(crate) fn apply(&self, cookie: &mut Cookie<'static>) {
+        if cookie.secure().is_none() {
+            cookie.set_secure(self.secure);
+        }
+        if cookie.http_only().is_none() {
+            cookie.set_http_only(self.http_only);
+        }
+        if cookie.same_site().is_none() {
+            cookie.set_same_site(self.same_site);
+        }
+        if cookie.path().is_none() {
+            if let Some(path) = &self.path {
+                cookie.set_path(path.clone());
+            }
+        }
     }
 }
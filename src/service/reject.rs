@@ -0,0 +1,116 @@
+use crate::Cookies;
+use http::{Request, Response, StatusCode};
+use pin_project_lite::pin_project;
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+use tower_layer::Layer;
+use tower_service::Service;
+
+/// Layer to apply [`RejectMalformedCookies`] middleware.
+///
+/// The middleware rejects requests with malformed `Cookie` headers with `400 Bad Request`,
+/// without calling the inner service. By default, [`CookieManager`](super::CookieManager) skips
+/// the malformed fragments, which can be inspected with [`Cookies::parse_errors`].
+///
+/// It relies on [`Cookies`], so it should be applied inside
+/// [`CookieManagerLayer`](crate::CookieManagerLayer):
+///
+/// ```
+/// use axum::{routing::get, Router};
+/// use tower::ServiceBuilder;
+/// use tower_cookies::{service::RejectMalformedCookiesLayer, CookieManagerLayer};
+///
+/// let app: Router = Router::new().route("/", get(|| async {})).layer(
+///     ServiceBuilder::new()
+///         .layer(CookieManagerLayer::new())
+///         .layer(RejectMalformedCookiesLayer::new()),
+/// );
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct RejectMalformedCookiesLayer {
+    _priv: (),
+}
+
+impl RejectMalformedCookiesLayer {
+    /// Create a new layer.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<S> Layer<S> for RejectMalformedCookiesLayer {
+    type Service = RejectMalformedCookies<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RejectMalformedCookies { inner }
+    }
+}
+
+/// Middleware rejecting requests with malformed `Cookie` headers.
+///
+/// Requests without [`Cookies`] in the extensions are passed through.
+#[derive(Clone, Debug)]
+pub struct RejectMalformedCookies<S> {
+    inner: S,
+}
+
+impl<ReqBody, ResBody, S> Service<Request<ReqBody>> for RejectMalformedCookies<S>
+where
+    S: Service<Request<ReqBody>, Response = Response<ResBody>>,
+    ResBody: Default,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = RejectMalformedCookiesFuture<S::Future>;
+
+    #[inline]
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request<ReqBody>) -> Self::Future {
+        let rejected = req
+            .extensions()
+            .get::<Cookies>()
+            .is_some_and(|cookies| !cookies.parse_errors().is_empty());
+        #[cfg(feature = "tracing")]
+        if rejected {
+            tracing::debug!("rejected a request with malformed cookies");
+        }
+        RejectMalformedCookiesFuture {
+            future: (!rejected).then(|| self.inner.call(req)),
+        }
+    }
+}
+
+pin_project! {
+    /// Response future for [`RejectMalformedCookies`].
+    #[derive(Debug)]
+    pub struct RejectMalformedCookiesFuture<F> {
+        // `None` if the request is rejected
+        #[pin]
+        future: Option<F>,
+    }
+}
+
+impl<F, ResBody, E> Future for RejectMalformedCookiesFuture<F>
+where
+    F: Future<Output = Result<Response<ResBody>, E>>,
+    ResBody: Default,
+{
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        match self.project().future.as_pin_mut() {
+            Some(future) => future.poll(cx),
+            None => {
+                let mut res = Response::new(ResBody::default());
+                *res.status_mut() = StatusCode::BAD_REQUEST;
+                Poll::Ready(Ok(res))
+            }
+        }
+    }
+}
@@ -0,0 +1,254 @@
+use super::{cache, CacheControl, Config, SkipCacheHeaders};
+use futures_util::ready;
+use http::{header, HeaderValue, Request, Response};
+use parking_lot::Mutex;
+use pin_project_lite::pin_project;
+use std::{
+    future::Future,
+    mem,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+use tower_layer::Layer;
+use tower_service::Service;
+
+/// What to do with the cookie changes when the inner service returns an error.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ErrorPolicy {
+    /// Drop the changes, as there's no response to write them to.
+    #[default]
+    Drop,
+    /// Put the `Set-Cookie` header values into [`StashedCookies`] found in the request
+    /// extensions, so an outer layer building the error response can still apply them. Without
+    /// [`StashedCookies`], the changes are dropped.
+    Stash,
+}
+
+/// A request extension collecting the `Set-Cookie` header values of a failed request.
+///
+/// [`ApplyStashedCookiesLayer`] inserts it into the requests and applies it to the responses, but
+/// it can also be managed manually by a custom error handling layer. All the clones refer to the
+/// same values.
+#[derive(Clone, Debug, Default)]
+pub struct StashedCookies {
+    inner: Arc<Mutex<Stash>>,
+}
+
+#[derive(Debug, Default)]
+struct Stash {
+    values: Vec<HeaderValue>,
+    /// The cache settings of the layer which stashed the values
+    cache_control: Option<CacheControl>,
+    vary_cookie: bool,
+}
+
+impl StashedCookies {
+    /// Creates an empty stash.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Takes the stashed `Set-Cookie` header values.
+    pub fn take(&self) -> Vec<HeaderValue> {
+        mem::take(&mut self.inner.lock().values)
+    }
+
+    /// Appends the stashed values to the `Set-Cookie` headers of the `response`, emptying the
+    /// stash.
+    ///
+    /// The `Cache-Control` and `Vary` headers configured on
+    /// [`CookieManagerLayer`](crate::CookieManagerLayer) are added too, unless the response has
+    /// the [`SkipCacheHeaders`] extension.
+    pub fn apply<B>(&self, response: &mut Response<B>) {
+        let (values, cache_control, vary_cookie) = {
+            let mut stash = self.inner.lock();
+            (
+                mem::take(&mut stash.values),
+                stash.cache_control,
+                stash.vary_cookie,
+            )
+        };
+        if values.is_empty() {
+            return;
+        }
+        let headers = response.headers_mut();
+        for value in values {
+            headers.append(header::SET_COOKIE, value);
+        }
+        if response.extensions().get::<SkipCacheHeaders>().is_none() {
+            cache::protect(response.headers_mut(), cache_control, vary_cookie);
+        }
+    }
+
+    pub(crate) fn extend(&self, values: Vec<HeaderValue>, config: &Config) {
+        if values.is_empty() {
+            return;
+        }
+        let mut stash = self.inner.lock();
+        stash.values.extend(values);
+        stash.cache_control = config.cache_control;
+        stash.vary_cookie = config.vary_cookie;
+    }
+}
+
+/// Layer to apply [`ApplyStashedCookies`] middleware.
+///
+/// Combined with [`ErrorPolicy::Stash`], the layer keeps the cookie changes of requests failed
+/// inside [`CookieManager`](super::CookieManager). It should wrap the layer converting the errors
+/// into responses:
+///
+/// ```
+/// use axum::{
+///     body::Body, error_handling::HandleErrorLayer, http::StatusCode, response::Response, Router,
+/// };
+/// use tower::{BoxError, ServiceBuilder};
+/// use tower_cookies::{
+///     service::{ApplyStashedCookiesLayer, ErrorPolicy},
+///     CookieManagerLayer,
+/// };
+///
+/// let service = ServiceBuilder::new()
+///     .layer(ApplyStashedCookiesLayer::new())
+///     .layer(HandleErrorLayer::new(|_: BoxError| async {
+///         StatusCode::INTERNAL_SERVER_ERROR
+///     }))
+///     .layer(CookieManagerLayer::new().error_policy(ErrorPolicy::Stash))
+///     .service_fn(|_| async { Err::<Response<Body>, BoxError>("failed".into()) });
+/// let app: Router = Router::new().route_service("/", service);
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct ApplyStashedCookiesLayer {
+    _priv: (),
+}
+
+impl ApplyStashedCookiesLayer {
+    /// Create a new layer.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<S> Layer<S> for ApplyStashedCookiesLayer {
+    type Service = ApplyStashedCookies<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        ApplyStashedCookies { inner }
+    }
+}
+
+/// Middleware inserting [`StashedCookies`] into requests and applying them to responses.
+#[derive(Clone, Debug)]
+pub struct ApplyStashedCookies<S> {
+    inner: S,
+}
+
+impl<ReqBody, ResBody, S> Service<Request<ReqBody>> for ApplyStashedCookies<S>
+where
+    S: Service<Request<ReqBody>, Response = Response<ResBody>>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = ApplyStashedCookiesFuture<S::Future>;
+
+    #[inline]
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut req: Request<ReqBody>) -> Self::Future {
+        let stash = StashedCookies::new();
+        req.extensions_mut().insert(stash.clone());
+        ApplyStashedCookiesFuture {
+            future: self.inner.call(req),
+            stash,
+        }
+    }
+}
+
+pin_project! {
+    /// Response future for [`ApplyStashedCookies`].
+    #[derive(Debug)]
+    pub struct ApplyStashedCookiesFuture<F> {
+        #[pin]
+        future: F,
+        stash: StashedCookies,
+    }
+}
+
+impl<F, ResBody, E> Future for ApplyStashedCookiesFuture<F>
+where
+    F: Future<Output = Result<Response<ResBody>, E>>,
+{
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let mut res = ready!(this.future.poll(cx)?);
+        this.stash.apply(&mut res);
+        Poll::Ready(Ok(res))
+    }
+}
+
+#[cfg(all(test, feature = "axum-core"))]
+mod tests {
+    use super::*;
+    use crate::{CookieManagerLayer, Cookies};
+    use axum::body::Body;
+    use cookie::Cookie;
+    use http::StatusCode;
+    use tower::{ServiceBuilder, ServiceExt};
+
+    async fn failing(req: Request<Body>) -> Result<Response<Body>, &'static str> {
+        let cookies = req.extensions().get::<Cookies>().unwrap();
+        cookies.add(Cookie::new("foo", "bar"));
+        Err("oops")
+    }
+
+    async fn handle_error(
+        res: Result<Response<Body>, &'static str>,
+    ) -> Result<Response<Body>, &'static str> {
+        Ok(res.unwrap_or_else(|_| {
+            Response::builder()
+                .status(StatusCode::INTERNAL_SERVER_ERROR)
+                .body(Body::empty())
+                .unwrap()
+        }))
+    }
+
+    #[tokio::test]
+    async fn stash() {
+        for (policy, expected) in [
+            (ErrorPolicy::Drop, None),
+            (ErrorPolicy::Stash, Some("foo=bar")),
+        ] {
+            let svc = ServiceBuilder::new()
+                .layer(ApplyStashedCookiesLayer::new())
+                .then(handle_error)
+                .layer(CookieManagerLayer::new().error_policy(policy))
+                .service_fn(failing);
+            let res = svc.oneshot(Request::new(Body::empty())).await.unwrap();
+            assert_eq!(res.status(), StatusCode::INTERNAL_SERVER_ERROR);
+            let value = res.headers().get(header::SET_COOKIE);
+            assert_eq!(value.map(|v| v.to_str().unwrap()), expected);
+        }
+    }
+
+    #[tokio::test]
+    async fn cache_headers() {
+        let svc = ServiceBuilder::new()
+            .layer(ApplyStashedCookiesLayer::new())
+            .then(handle_error)
+            .layer(
+                CookieManagerLayer::new()
+                    .error_policy(ErrorPolicy::Stash)
+                    .cache_control(CacheControl::NoStore)
+                    .vary_cookie(true),
+            )
+            .service_fn(failing);
+        let res = svc.oneshot(Request::new(Body::empty())).await.unwrap();
+        assert_eq!(res.headers()[header::SET_COOKIE], "foo=bar");
+        assert_eq!(res.headers()[header::CACHE_CONTROL], "no-store");
+        assert_eq!(res.headers()[header::VARY], "Cookie");
+    }
+}
@@ -0,0 +1,537 @@
+//! Cookie-backed sessions.
+//!
+//! Sessions are enabled by [`CookieManagerLayer::with_session`], which requires a key set by
+//! [`CookieManagerLayer::with_key`]. By default, the session data is kept on the client in a
+//! private cookie. With [`SessionConfig::with_store`], only a session id is kept in the cookie,
+//! while the data goes to a [`SessionStore`].
+//!
+//! The session is loaded on the first access and persisted after the inner service responds,
+//! only if it was changed during the request. The accessors are async, as they may load the
+//! session from the store.
+//!
+//! # Example:
+//! ```
+//! use axum::{routing::get, Router};
+//! use tower_cookies::{
+//!     session::{MemoryStore, Session, SessionConfig},
+//!     CookieManagerLayer, Key,
+//! };
+//!
+//! async fn handler(session: Session) -> String {
+//!     let visits = session.get::<usize>("visits").await.unwrap_or(0) + 1;
+//!     session.insert("visits", &visits).await.unwrap();
+//!     format!("You have visited this page {visits} times")
+//! }
+//!
+//! let app: Router = Router::new().route("/", get(handler)).layer(
+//!     CookieManagerLayer::new()
+//!         .with_key(Key::generate())
+//!         .with_session(SessionConfig::new().with_store(MemoryStore::new())),
+//! );
+//! ```
+//!
+//! [`CookieManagerLayer::with_session`]: crate::CookieManagerLayer::with_session
+//! [`CookieManagerLayer::with_key`]: crate::CookieManagerLayer::with_key
+
+use crate::{
+    typed::{Codec, Json},
+    Cookies, PrivateCookies,
+};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use cookie::{time::Duration, Cookie};
+use parking_lot::Mutex;
+use serde::{de::DeserializeOwned, Serialize};
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    fmt,
+    future::Future,
+    io, mem,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+pub use self::store::{BoxFuture, FileStore, MemoryStore, SessionStore};
+
+mod store;
+
+/// Key-value data of a session.
+pub type SessionData = HashMap<String, serde_json::Value>;
+
+/// Configuration of sessions.
+#[derive(Clone, Debug)]
+pub struct SessionConfig {
+    cookie_name: Cow<'static, str>,
+    max_age: Option<Duration>,
+    store: Option<Arc<dyn SessionStore>>,
+    on_store_error: Option<StoreErrorHook>,
+}
+
+impl Default for SessionConfig {
+    fn default() -> Self {
+        Self {
+            cookie_name: "session".into(),
+            max_age: None,
+            store: None,
+            on_store_error: None,
+        }
+    }
+}
+
+impl SessionConfig {
+    /// Creates a configuration of sessions stored in a private cookie named `session`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the name of the session cookie.
+    pub fn cookie_name(mut self, name: impl Into<Cow<'static, str>>) -> Self {
+        self.cookie_name = name.into();
+        self
+    }
+
+    /// Sets the `Max-Age` of the session cookie. By default, the cookie expires when the browser
+    /// is closed.
+    pub fn max_age(mut self, value: Duration) -> Self {
+        self.max_age = Some(value);
+        self
+    }
+
+    /// Stores the session data in the server-side `store`, keeping only the session id in the
+    /// cookie.
+    pub fn with_store(mut self, store: impl SessionStore) -> Self {
+        self.store = Some(Arc::new(store));
+        self
+    }
+
+    /// Sets a hook called for the errors of the store. A session which fails to load is empty
+    /// for the request and isn't saved, so the stored data isn't overwritten.
+    ///
+    /// ```
+    /// use tower_cookies::session::{MemoryStore, SessionConfig};
+    ///
+    /// let config = SessionConfig::new()
+    ///     .with_store(MemoryStore::new())
+    ///     .on_store_error(|error| eprintln!("session store: {error}"));
+    /// ```
+    pub fn on_store_error<F>(mut self, hook: F) -> Self
+    where
+        F: Fn(&io::Error) + Send + Sync + 'static,
+    {
+        self.on_store_error = Some(StoreErrorHook(Arc::new(hook)));
+        self
+    }
+
+    fn report(&self, error: &io::Error) {
+        #[cfg(feature = "tracing")]
+        tracing::warn!(%error, "session store error");
+        if let Some(hook) = &self.on_store_error {
+            (hook.0)(error);
+        }
+    }
+
+    fn cookie(&self, value: String) -> Cookie<'static> {
+        let mut cookie = Cookie::build((self.cookie_name.clone(), value))
+            .path("/")
+            .http_only(true);
+        if let Some(max_age) = self.max_age {
+            cookie = cookie.max_age(max_age);
+        }
+        cookie.build()
+    }
+}
+
+/// A hook set by [`SessionConfig::on_store_error`].
+#[derive(Clone)]
+struct StoreErrorHook(Arc<dyn Fn(&io::Error) + Send + Sync>);
+
+impl fmt::Debug for StoreErrorHook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("StoreErrorHook")
+    }
+}
+
+/// A session of the current request.
+///
+/// The session is cheap to clone, all the clones refer to the same data.
+#[derive(Clone, Debug)]
+pub struct Session {
+    inner: Arc<Mutex<Inner>>,
+}
+
+#[derive(Debug)]
+struct Inner {
+    cookies: Cookies,
+    config: Arc<SessionConfig>,
+    state: Option<State>,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    id: Option<String>,
+    data: SessionData,
+    changed: bool,
+    stale_ids: Vec<String>,
+    /// The store failed to load the session, so it isn't saved
+    load_failed: bool,
+}
+
+impl Session {
+    pub(crate) fn new(cookies: Cookies, config: Arc<SessionConfig>) -> Self {
+        let inner = Inner {
+            cookies,
+            config,
+            state: None,
+        };
+        Self {
+            inner: Arc::new(Mutex::new(inner)),
+        }
+    }
+
+    /// Returns the value stored under the `key`. Returns [`None`] if the key doesn't exist or the
+    /// value can't be deserialized into `T`.
+    pub async fn get<T: DeserializeOwned>(&self, key: &str) -> Option<T> {
+        self.load().await;
+        let value = self.with_state(|state| state.data.get(key).cloned())?;
+        serde_json::from_value(value).ok()
+    }
+
+    /// Stores the `value` under the `key`, replacing the existing value.
+    pub async fn insert<T: Serialize + ?Sized>(
+        &self,
+        key: impl Into<String>,
+        value: &T,
+    ) -> Result<(), serde_json::Error> {
+        let value = serde_json::to_value(value)?;
+        self.load().await;
+        self.with_state(|state| {
+            state.data.insert(key.into(), value);
+            state.changed = true;
+        });
+        Ok(())
+    }
+
+    /// Removes the value stored under the `key`, returning it if it can be deserialized into `T`.
+    pub async fn remove<T: DeserializeOwned>(&self, key: &str) -> Option<T> {
+        self.load().await;
+        let value = self.with_state(|state| {
+            let value = state.data.remove(key)?;
+            state.changed = true;
+            Some(value)
+        })?;
+        serde_json::from_value(value).ok()
+    }
+
+    /// Removes all the values. An empty session is deleted when the response is sent.
+    pub async fn clear(&self) {
+        self.load().await;
+        self.with_state(|state| {
+            state.data.clear();
+            state.changed = true;
+        });
+    }
+
+    /// Assigns a new id to the session keeping its data, e.g. after the user logs in to prevent
+    /// session fixation. The old server-side session is deleted.
+    ///
+    /// Sessions without a store have no id, so the session cookie is just re-issued.
+    pub async fn regenerate_id(&self) {
+        self.load().await;
+        self.with_state(|state| {
+            if let Some(id) = state.id.take() {
+                state.stale_ids.push(id);
+            }
+            state.changed = true;
+        });
+    }
+
+    /// Returns the id of a server-side session. Returns [`None`] for sessions without a store
+    /// and for new sessions, which get an id when they're saved.
+    pub async fn id(&self) -> Option<String> {
+        self.load().await;
+        self.with_state(|state| state.id.clone())
+    }
+
+    /// Loads the session on the first access. The lock isn't held while the store loads it.
+    async fn load(&self) {
+        let (cookies, config) = {
+            let inner = self.inner.lock();
+            if inner.state.is_some() {
+                return;
+            }
+            (inner.cookies.clone(), inner.config.clone())
+        };
+        let state = State::load(&cookies, &config).await;
+        self.inner.lock().state.get_or_insert(state);
+    }
+
+    fn with_state<R>(&self, f: impl FnOnce(&mut State) -> R) -> R {
+        let mut inner = self.inner.lock();
+        f(inner.state.as_mut().expect("the session is loaded"))
+    }
+
+    /// Saves the session if it was changed. A session without a store is saved right away, a
+    /// server-side one by the returned future.
+    pub(crate) fn persist(&self) -> Option<Persist> {
+        let mut inner = self.inner.lock();
+        let Inner {
+            cookies,
+            config,
+            state,
+        } = &mut *inner;
+        let state = state.as_mut().filter(|s| s.changed && !s.load_failed)?;
+        state.changed = false;
+
+        let Some(store) = config.store.clone() else {
+            let jar = private(cookies);
+            if state.data.is_empty() {
+                jar.remove(config.cookie(String::new()));
+            } else if let Ok(value) = Json::encode(&state.data) {
+                jar.add(config.cookie(value));
+            }
+            return None;
+        };
+
+        let mut stale_ids = mem::take(&mut state.stale_ids);
+        let id = if state.data.is_empty() {
+            stale_ids.extend(state.id.take());
+            None
+        } else {
+            Some(state.id.get_or_insert_with(generate_id).clone())
+        };
+        let data = state.data.clone();
+        let cookies = cookies.clone();
+        let config = config.clone();
+        Some(Persist(Box::pin(async move {
+            for id in stale_ids {
+                if let Err(error) = store.delete(&id).await {
+                    config.report(&error);
+                }
+            }
+            let jar = private(&cookies);
+            let Some(id) = id else {
+                jar.remove(config.cookie(String::new()));
+                return;
+            };
+            match store.save(&id, &data).await {
+                Ok(()) => jar.add(config.cookie(id)),
+                Err(error) => config.report(&error),
+            }
+        })))
+    }
+}
+
+/// Saves a server-side session, returned by [`Session::persist`].
+pub(crate) struct Persist(BoxFuture<'static, ()>);
+
+impl Future for Persist {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        self.0.as_mut().poll(cx)
+    }
+}
+
+impl fmt::Debug for Persist {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Persist")
+    }
+}
+
+impl State {
+    async fn load(cookies: &Cookies, config: &SessionConfig) -> Self {
+        let Some(cookie) = private(cookies).get(&config.cookie_name) else {
+            return Self::default();
+        };
+        let Some(store) = &config.store else {
+            return Self {
+                data: Json::decode(cookie.value()).unwrap_or_default(),
+                ..Default::default()
+            };
+        };
+        match store.load(cookie.value()).await {
+            Ok(Some(data)) => Self {
+                id: Some(cookie.value().to_owned()),
+                data,
+                ..Default::default()
+            },
+            Ok(None) => Self::default(),
+            Err(error) => {
+                config.report(&error);
+                Self {
+                    load_failed: true,
+                    ..Default::default()
+                }
+            }
+        }
+    }
+}
+
+fn private(cookies: &Cookies) -> PrivateCookies<'static> {
+    cookies
+        .private_owned()
+        .expect("sessions require a key set by `CookieManagerLayer::with_key`")
+}
+
+fn generate_id() -> String {
+    URL_SAFE_NO_PAD.encode(rand::random::<[u8; 32]>())
+}
+
+#[cfg(all(test, feature = "axum-core"))]
+mod tests {
+    use super::*;
+    use crate::{
+        test_helpers::{body_string, call, set_cookie},
+        CookieManagerLayer,
+    };
+    use axum::{body::Body, routing::get, Router};
+    use cookie::Key;
+    use http::Request;
+
+    fn app(config: SessionConfig) -> Router {
+        Router::new()
+            .route(
+                "/get",
+                get(|session: Session| async move {
+                    session.get::<String>("foo").await.unwrap_or_default()
+                }),
+            )
+            .route(
+                "/set",
+                get(|session: Session| async move { session.insert("foo", "bar").await.unwrap() }),
+            )
+            .route(
+                "/regenerate",
+                get(|session: Session| async move { session.regenerate_id().await }),
+            )
+            .route(
+                "/clear",
+                get(|session: Session| async move { session.clear().await }),
+            )
+            .layer(
+                CookieManagerLayer::new()
+                    .with_key(Key::from(&[1; 64]))
+                    .with_session(config),
+            )
+    }
+
+    fn private_cookie(name: &str, value: &str) -> String {
+        let mut jar = cookie::CookieJar::new();
+        jar.private_mut(&Key::from(&[1; 64]))
+            .add(Cookie::new(name.to_owned(), value.to_owned()));
+        jar.get(name).unwrap().stripped().to_string()
+    }
+
+    #[tokio::test]
+    async fn client_side() {
+        let app = app(SessionConfig::new());
+        let res = call(&app, "/get", None).await;
+        assert_eq!(set_cookie(&res), None);
+
+        let res = call(&app, "/set", None).await;
+        let cookie = set_cookie(&res).unwrap();
+        assert!(cookie.starts_with("session="));
+        assert!(!cookie.contains("bar"));
+
+        let res = call(&app, "/get", Some(&cookie)).await;
+        assert_eq!(set_cookie(&res), None);
+        assert_eq!(body_string(res).await, "bar");
+
+        let res = call(&app, "/clear", Some(&cookie)).await;
+        assert_eq!(set_cookie(&res).unwrap(), "session=");
+    }
+
+    #[tokio::test]
+    async fn server_side() {
+        let app = app(SessionConfig::new().with_store(MemoryStore::new()));
+        let res = call(&app, "/set", None).await;
+        let cookie = set_cookie(&res).unwrap();
+
+        let res = call(&app, "/get", Some(&cookie)).await;
+        assert_eq!(body_string(res).await, "bar");
+
+        let res = call(&app, "/regenerate", Some(&cookie)).await;
+        let new_cookie = set_cookie(&res).unwrap();
+        assert_ne!(cookie, new_cookie);
+
+        let res = call(&app, "/get", Some(&cookie)).await;
+        assert_eq!(body_string(res).await, "");
+        let res = call(&app, "/get", Some(&new_cookie)).await;
+        assert_eq!(body_string(res).await, "bar");
+    }
+
+    #[derive(Debug)]
+    struct FailingStore;
+
+    impl SessionStore for FailingStore {
+        fn load<'a>(&'a self, _id: &'a str) -> BoxFuture<'a, io::Result<Option<SessionData>>> {
+            Box::pin(async { Err(io::Error::new(io::ErrorKind::Other, "load")) })
+        }
+
+        fn save<'a>(
+            &'a self,
+            _id: &'a str,
+            _data: &'a SessionData,
+        ) -> BoxFuture<'a, io::Result<()>> {
+            Box::pin(async { Err(io::Error::new(io::ErrorKind::Other, "save")) })
+        }
+
+        fn delete<'a>(&'a self, _id: &'a str) -> BoxFuture<'a, io::Result<()>> {
+            Box::pin(async { Err(io::Error::new(io::ErrorKind::Other, "delete")) })
+        }
+    }
+
+    #[tokio::test]
+    async fn store_errors() {
+        let errors = Arc::new(Mutex::new(Vec::new()));
+        let hook_errors = errors.clone();
+        let app = app(SessionConfig::new()
+            .with_store(FailingStore)
+            .on_store_error(move |error| hook_errors.lock().push(error.to_string())));
+        let res = call(&app, "/set", None).await;
+        assert_eq!(set_cookie(&res), None);
+        assert_eq!(*errors.lock(), ["save"]);
+
+        // A session failing to load isn't saved
+        let cookie = private_cookie("session", "id");
+        let res = call(&app, "/set", Some(&cookie)).await;
+        assert_eq!(set_cookie(&res), None);
+        assert_eq!(*errors.lock(), ["save", "load"]);
+    }
+
+    #[tokio::test]
+    async fn file_store() {
+        let dir = std::env::temp_dir().join(format!("tower-cookies-app-{}", std::process::id()));
+        let app = app(SessionConfig::new().with_store(FileStore::new(&dir).unwrap()));
+        let res = call(&app, "/set", None).await;
+        let cookie = set_cookie(&res).unwrap();
+        let res = call(&app, "/get", Some(&cookie)).await;
+        assert_eq!(body_string(res).await, "bar");
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    fn manager(config: SessionConfig) -> crate::CookieManager<Router> {
+        crate::CookieManager::new(Router::new().route(
+            "/",
+            get(|session: Session| async move { session.id().await.unwrap_or_default() }),
+        ))
+        .with_session(config)
+    }
+
+    #[tokio::test]
+    async fn manager_key_after_session() {
+        let svc = manager(SessionConfig::new()).with_key(Key::from(&[1; 64]));
+        let res = tower::ServiceExt::oneshot(svc, Request::new(Body::empty()))
+            .await
+            .unwrap();
+        assert!(res.status().is_success());
+    }
+
+    #[tokio::test]
+    #[should_panic(expected = "sessions require a key")]
+    async fn manager_without_key() {
+        let svc = manager(SessionConfig::new());
+        let _ = tower::ServiceExt::oneshot(svc, Request::new(Body::empty())).await;
+    }
+}
@@ -0,0 +1,198 @@
+//! Server-side session storage.
+
+use super::SessionData;
+use parking_lot::Mutex;
+use std::{
+    collections::HashMap,
+    fmt, fs,
+    future::{self, Future},
+    io,
+    path::{Path, PathBuf},
+    pin::Pin,
+};
+
+/// A boxed future returned by [`SessionStore`].
+pub type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+/// A server-side storage of session data.
+///
+/// A session is loaded on the first access in a handler and saved after the inner service
+/// responds, before the response is sent. The errors are reported to the hook set by
+/// [`SessionConfig::on_store_error`](super::SessionConfig::on_store_error). If a session can't
+/// be saved, the session cookie isn't updated.
+///
+/// # Example:
+/// ```
+/// use std::io;
+/// use tower_cookies::session::{BoxFuture, SessionData, SessionStore};
+///
+/// #[derive(Debug)]
+/// struct NullStore;
+///
+/// impl SessionStore for NullStore {
+///     fn load<'a>(&'a self, _id: &'a str) -> BoxFuture<'a, io::Result<Option<SessionData>>> {
+///         Box::pin(async { Ok(None) })
+///     }
+///
+///     fn save<'a>(&'a self, _id: &'a str, _data: &'a SessionData) -> BoxFuture<'a, io::Result<()>> {
+///         Box::pin(async { Ok(()) })
+///     }
+///
+///     fn delete<'a>(&'a self, _id: &'a str) -> BoxFuture<'a, io::Result<()>> {
+///         Box::pin(async { Ok(()) })
+///     }
+/// }
+/// ```
+pub trait SessionStore: fmt::Debug + Send + Sync + 'static {
+    /// Loads the data of the session `id`. Returns [`None`] if there's no such session.
+    fn load<'a>(&'a self, id: &'a str) -> BoxFuture<'a, io::Result<Option<SessionData>>>;
+
+    /// Saves the `data` of the session `id`.
+    fn save<'a>(&'a self, id: &'a str, data: &'a SessionData) -> BoxFuture<'a, io::Result<()>>;
+
+    /// Deletes the session `id`. Deleting a missing session isn't an error.
+    fn delete<'a>(&'a self, id: &'a str) -> BoxFuture<'a, io::Result<()>>;
+}
+
+/// An in-memory session store.
+///
+/// Sessions are lost on restart and aren't shared between processes, so it's mostly useful for
+/// development and tests.
+#[derive(Debug, Default)]
+pub struct MemoryStore {
+    sessions: Mutex<HashMap<String, SessionData>>,
+}
+
+impl MemoryStore {
+    /// Creates an empty store.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl SessionStore for MemoryStore {
+    fn load<'a>(&'a self, id: &'a str) -> BoxFuture<'a, io::Result<Option<SessionData>>> {
+        let data = self.sessions.lock().get(id).cloned();
+        Box::pin(future::ready(Ok(data)))
+    }
+
+    fn save<'a>(&'a self, id: &'a str, data: &'a SessionData) -> BoxFuture<'a, io::Result<()>> {
+        self.sessions.lock().insert(id.to_owned(), data.clone());
+        Box::pin(future::ready(Ok(())))
+    }
+
+    fn delete<'a>(&'a self, id: &'a str) -> BoxFuture<'a, io::Result<()>> {
+        self.sessions.lock().remove(id);
+        Box::pin(future::ready(Ok(())))
+    }
+}
+
+/// A session store keeping every session as a JSON file in a directory.
+///
+/// The files are read and written synchronously, blocking the async executor for the time of
+/// the I/O, so like [`MemoryStore`], it's mostly useful for development and tests. Production
+/// backends, e.g. a database, should implement [`SessionStore`] with async I/O.
+#[derive(Debug)]
+pub struct FileStore {
+    dir: PathBuf,
+}
+
+impl FileStore {
+    /// Creates a store in the `dir`, creating the directory if it doesn't exist.
+    pub fn new(dir: impl Into<PathBuf>) -> io::Result<Self> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)?;
+        Ok(Self { dir })
+    }
+
+    /// Returns the directory of the store.
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    fn path(&self, id: &str) -> io::Result<PathBuf> {
+        if id.is_empty()
+            || !id
+                .bytes()
+                .all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_')
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "invalid session id",
+            ));
+        }
+        Ok(self.dir.join(format!("{id}.json")))
+    }
+
+    fn load_sync(&self, id: &str) -> io::Result<Option<SessionData>> {
+        let bytes = match fs::read(self.path(id)?) {
+            Ok(bytes) => bytes,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        Ok(Some(serde_json::from_slice(&bytes)?))
+    }
+
+    fn save_sync(&self, id: &str, data: &SessionData) -> io::Result<()> {
+        let path = self.path(id)?;
+        let tmp = path.with_extension("tmp");
+        fs::write(&tmp, serde_json::to_vec(data)?)?;
+        fs::rename(tmp, path)
+    }
+
+    fn delete_sync(&self, id: &str) -> io::Result<()> {
+        match fs::remove_file(self.path(id)?) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl SessionStore for FileStore {
+    fn load<'a>(&'a self, id: &'a str) -> BoxFuture<'a, io::Result<Option<SessionData>>> {
+        Box::pin(future::ready(self.load_sync(id)))
+    }
+
+    fn save<'a>(&'a self, id: &'a str, data: &'a SessionData) -> BoxFuture<'a, io::Result<()>> {
+        Box::pin(future::ready(self.save_sync(id, data)))
+    }
+
+    fn delete<'a>(&'a self, id: &'a str) -> BoxFuture<'a, io::Result<()>> {
+        Box::pin(future::ready(self.delete_sync(id)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn data() -> SessionData {
+        let mut data = SessionData::new();
+        data.insert("user".into(), 1.into());
+        data
+    }
+
+    #[tokio::test]
+    async fn memory() {
+        let store = MemoryStore::new();
+        assert!(store.load("foo").await.unwrap().is_none());
+        store.save("foo", &data()).await.unwrap();
+        assert_eq!(store.load("foo").await.unwrap(), Some(data()));
+        store.delete("foo").await.unwrap();
+        assert!(store.load("foo").await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn file() {
+        let dir = std::env::temp_dir().join(format!("tower-cookies-{}", std::process::id()));
+        let store = FileStore::new(&dir).unwrap();
+        assert!(store.load("foo").await.unwrap().is_none());
+        store.save("foo", &data()).await.unwrap();
+        assert_eq!(store.load("foo").await.unwrap(), Some(data()));
+        store.delete("foo").await.unwrap();
+        assert!(store.load("foo").await.unwrap().is_none());
+        store.delete("foo").await.unwrap();
+        assert!(store.load("../foo").await.is_err());
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
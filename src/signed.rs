@@ -1,20 +1,21 @@
-use crate::Cookies;
-use cookie::{Cookie, Key};
+use crate::{key::KeyRef, Cookies};
+use cookie::Cookie;
 
 /// A child cookie jar that authenticates its cookies.
 /// It signs all the cookies added to it and verifies cookies retrieved from it.
 /// Any cookies stored in `SignedCookies` are provided integrity and authenticity. In other
 /// words, clients cannot tamper with the contents of a cookie nor can they fabricate cookie
 /// values, but the data is visible in plaintext.
+#[derive(Clone, Debug)]
 pub struct SignedCookies<'a> {
     cookies: Cookies,
-    key: &'a Key,
+    key: KeyRef<'a>,
 }
 
 impl<'a> SignedCookies<'a> {
     /// Creates an instance of `SignedCookies` with parent `cookies` and key `key`. This method is
     /// typically called indirectly via the `signed` method of [`Cookies`].
-    pub(crate) fn new(cookies: &Cookies, key: &'a Key) -> Self {
+    pub(crate) fn new(cookies: &Cookies, key: KeyRef<'a>) -> Self {
         Self {
             cookies: cookies.clone(),
             key,
@@ -26,7 +27,7 @@ impl<'a> SignedCookies<'a> {
     pub fn add(&self, cookie: Cookie<'static>) {
         let mut inner = self.cookies.inner.lock();
         inner.changed = true;
-        inner.jar().signed_mut(self.key).add(cookie);
+        inner.jar().signed_mut(self.key.primary()).add(cookie);
     }
 
     /// Returns `Cookie` with the `name` and verifies the authenticity and integrity of the
@@ -34,7 +35,29 @@ impl<'a> SignedCookies<'a> {
     /// found, or the cookie fails to verify, None is returned.
     pub fn get(&self, name: &str) -> Option<Cookie<'static>> {
         let mut inner = self.cookies.inner.lock();
-        inner.jar().signed(self.key).get(name)
+        let jar = inner.jar();
+        if let Some(cookie) = jar.signed(self.key.primary()).get(name) {
+            return Some(cookie);
+        }
+        let Some(cookie) = self
+            .key
+            .retired()
+            .iter()
+            .find_map(|key| jar.signed(key).get(name))
+        else {
+            #[cfg(feature = "tracing")]
+            if jar.get(name).is_some() {
+                tracing::debug!(name, "failed to verify a signed cookie");
+            }
+            return None;
+        };
+        #[cfg(feature = "tracing")]
+        tracing::debug!(name, "signed cookie matched a retired key");
+        if self.key.reissue() {
+            jar.signed_mut(self.key.primary()).add(cookie.clone());
+            inner.changed = true;
+        }
+        Some(cookie)
     }
 
     /// Removes the `cookie` from the parent jar.
@@ -46,12 +69,21 @@ impl<'a> SignedCookies<'a> {
     pub fn remove(&self, cookie: Cookie<'static>) {
         self.cookies.remove(cookie);
     }
+
+    /// Removes the cookie named `name` from the parent jar, using the attributes registered by
+    /// [`CookieManagerLayer::register`](crate::CookieManagerLayer::register).
+    ///
+    /// See [`Cookies::remove_by_name`] for details.
+    pub fn remove_by_name(&self, name: &str) {
+        self.cookies.remove_by_name(name);
+    }
 }
 
 #[cfg(all(test, feature = "signed"))]
 mod tests {
-    use crate::Cookies;
+    use crate::{service::Config, Cookies, KeyRing};
     use cookie::{Cookie, Key};
+    use std::sync::Arc;
 
     #[test]
     fn get_absent() {
@@ -108,4 +140,45 @@ mod tests {
         signed.remove(cookie);
         assert!(signed.get("foo").is_none());
     }
+
+    #[test]
+    fn owned() {
+        let key = Key::generate();
+        let cookies = Cookies::new(vec![]);
+        assert!(cookies.signed_owned().is_none());
+        let cookies = cookies.with_config(Arc::new(Config {
+            keys: Some(Arc::new(KeyRing::new(key.clone()))),
+            ..Default::default()
+        }));
+        let cookie = Cookie::new("foo", "bar");
+        cookies.signed_owned().unwrap().add(cookie.clone());
+        assert_eq!(cookies.signed(&key).get("foo").unwrap(), cookie);
+    }
+
+    #[test]
+    fn retired_key() {
+        let old = Key::generate();
+        let cookies = Cookies::new(vec![]);
+        let cookie = Cookie::new("foo", "bar");
+        cookies.signed(&old).add(cookie.clone());
+
+        let ring = KeyRing::new(Key::generate()).with_retired(old.clone());
+        assert_eq!(cookies.signed_with_ring(&ring).get("foo").unwrap(), cookie);
+        assert_eq!(cookies.signed(ring.primary()).get("foo"), None);
+    }
+
+    #[test]
+    fn reissue() {
+        let old = Key::generate();
+        let cookies = Cookies::new(vec![]);
+        let cookie = Cookie::new("foo", "bar");
+        cookies.signed(&old).add(cookie.clone());
+
+        let ring = KeyRing::new(Key::generate())
+            .with_retired(old.clone())
+            .reissue(true);
+        assert_eq!(cookies.signed_with_ring(&ring).get("foo").unwrap(), cookie);
+        assert_eq!(cookies.signed(ring.primary()).get("foo").unwrap(), cookie);
+        assert_eq!(cookies.signed(&old).get("foo"), None);
+    }
 }
@@ -0,0 +1,313 @@
+//! Testing of cookie round-trips without a server.
+//!
+//! [`TestClient`] wraps a service, e.g. an axum `Router`, and keeps a [`ClientJar`] across the
+//! requests, so the cookies set by a response are sent with the following requests like a browser
+//! does.
+//!
+//! ```
+//! use axum::{routing::get, Router};
+//! use tower_cookies::{test::TestClient, Cookie, CookieManagerLayer, Cookies};
+//!
+//! # tokio::runtime::Runtime::new().unwrap().block_on(async {
+//! let app = Router::new()
+//!     .route("/login", get(|cookies: Cookies| async move {
+//!         cookies.add(Cookie::new("user", "alice"));
+//!     }))
+//!     .route("/logout", get(|cookies: Cookies| async move {
+//!         cookies.remove(Cookie::new("user", ""));
+//!     }))
+//!     .layer(CookieManagerLayer::new());
+//! let mut client = TestClient::new(app);
+//!
+//! let res = client.get("/login").await.unwrap();
+//! assert_eq!(res.assert_cookie_set("user").value(), "alice");
+//! assert_eq!(client.get_cookie("user").unwrap().value(), "alice");
+//!
+//! let res = client.get("/logout").await.unwrap();
+//! res.assert_cookie_removed("user");
+//! assert!(client.get_cookie("user").is_none());
+//! # });
+//! ```
+
+use crate::{client::ClientJar, service::future::is_removal};
+use cookie::Cookie;
+#[cfg(any(feature = "signed", feature = "private"))]
+use cookie::{CookieJar, Key};
+use futures_util::future::poll_fn;
+use http::{header, Request, Response, Uri};
+use std::ops::{Deref, DerefMut};
+use tower_service::Service;
+
+/// A client sending requests to a service and keeping the cookies between them.
+#[derive(Clone, Debug)]
+pub struct TestClient<S> {
+    service: S,
+    jar: ClientJar,
+    base_uri: Uri,
+}
+
+impl<S> TestClient<S> {
+    /// Creates a client of the `service` with an empty jar.
+    ///
+    /// Relative request URIs are resolved against `https://localhost`, so cookies with the
+    /// `Secure` attribute are kept.
+    pub fn new(service: S) -> Self {
+        Self {
+            service,
+            jar: ClientJar::new(),
+            base_uri: Uri::from_static("https://localhost"),
+        }
+    }
+
+    /// Sets the scheme and host relative request URIs are resolved against, e.g.
+    /// `http://example.com`.
+    pub fn base_uri(mut self, uri: Uri) -> Self {
+        self.base_uri = uri;
+        self
+    }
+
+    /// Returns the jar of the client.
+    pub fn jar(&self) -> &ClientJar {
+        &self.jar
+    }
+
+    /// Returns the cookie named `name` which would be sent with a request to the base URI.
+    pub fn get_cookie(&self, name: &str) -> Option<Cookie<'static>> {
+        self.jar
+            .matching(&self.base_uri)
+            .into_iter()
+            .find(|c| c.name() == name)
+    }
+
+    /// Adds the `cookie` to the jar, as if it was set by the base URI. The `Path` attribute
+    /// defaults to `/`.
+    pub fn add_cookie(&self, mut cookie: Cookie<'static>) {
+        if cookie.path().is_none() {
+            cookie.set_path("/");
+        }
+        self.jar.insert(&self.base_uri, cookie);
+    }
+
+    /// Adds the `cookie` signed with the `key` to the jar, as if it was added to
+    /// [`SignedCookies`](crate::SignedCookies) by the service.
+    #[cfg(feature = "signed")]
+    pub fn add_signed_cookie(&self, cookie: Cookie<'static>, key: &Key) {
+        let mut jar = CookieJar::new();
+        jar.signed_mut(key).add(cookie);
+        jar.delta().for_each(|c| self.add_cookie(c.clone()));
+    }
+
+    /// Adds the `cookie` encrypted with the `key` to the jar, as if it was added to
+    /// [`PrivateCookies`](crate::PrivateCookies) by the service.
+    #[cfg(feature = "private")]
+    pub fn add_private_cookie(&self, cookie: Cookie<'static>, key: &Key) {
+        let mut jar = CookieJar::new();
+        jar.private_mut(key).add(cookie);
+        jar.delta().for_each(|c| self.add_cookie(c.clone()));
+    }
+
+    /// Returns the cookie named `name` verified with the `key`. Returns [`None`] if the cookie
+    /// doesn't exist or fails to verify.
+    #[cfg(feature = "signed")]
+    pub fn get_signed_cookie(&self, name: &str, key: &Key) -> Option<Cookie<'static>> {
+        let mut jar = CookieJar::new();
+        jar.add_original(self.get_cookie(name)?);
+        jar.signed(key).get(name)
+    }
+
+    /// Returns the cookie named `name` decrypted with the `key`. Returns [`None`] if the cookie
+    /// doesn't exist or fails to decrypt.
+    #[cfg(feature = "private")]
+    pub fn get_private_cookie(&self, name: &str, key: &Key) -> Option<Cookie<'static>> {
+        let mut jar = CookieJar::new();
+        jar.add_original(self.get_cookie(name)?);
+        jar.private(key).get(name)
+    }
+
+    /// Sends the `request` with the matching cookies of the jar, storing the cookies of the
+    /// response.
+    pub async fn send<ReqBody, ResBody>(
+        &mut self,
+        mut request: Request<ReqBody>,
+    ) -> Result<TestResponse<ResBody>, S::Error>
+    where
+        S: Service<Request<ReqBody>, Response = Response<ResBody>>,
+    {
+        let uri = self.absolute_uri(request.uri());
+        if let Some(value) = self.jar.cookie_header(&uri) {
+            request.headers_mut().append(header::COOKIE, value);
+        }
+        poll_fn(|cx| self.service.poll_ready(cx)).await?;
+        let response = self.service.call(request).await?;
+        self.jar.insert_from_headers(&uri, response.headers());
+        Ok(TestResponse { response })
+    }
+
+    /// Sends a `GET` request to the `uri` with an empty [`String`] body, which suits e.g. axum
+    /// routers. Use [`TestClient::send`] for other body types.
+    pub async fn get<ResBody>(&mut self, uri: &str) -> Result<TestResponse<ResBody>, S::Error>
+    where
+        S: Service<Request<String>, Response = Response<ResBody>>,
+    {
+        let request = Request::get(uri)
+            .body(String::new())
+            .expect("invalid request URI");
+        self.send(request).await
+    }
+
+    fn absolute_uri(&self, uri: &Uri) -> Uri {
+        if uri.host().is_some() {
+            return uri.clone();
+        }
+        let mut parts = uri.clone().into_parts();
+        parts.scheme = self.base_uri.scheme().cloned();
+        parts.authority = self.base_uri.authority().cloned();
+        Uri::from_parts(parts).unwrap_or_else(|_| self.base_uri.clone())
+    }
+}
+
+/// A response received by [`TestClient`], with assertions of the cookies it sets.
+///
+/// The response dereferences to [`Response`].
+#[derive(Debug)]
+pub struct TestResponse<B> {
+    response: Response<B>,
+}
+
+impl<B> TestResponse<B> {
+    /// Returns the cookies of the `Set-Cookie` headers, including the removal ones.
+    pub fn set_cookies(&self) -> Vec<Cookie<'static>> {
+        self.response
+            .headers()
+            .get_all(header::SET_COOKIE)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .filter_map(|value| Cookie::parse(value.to_owned()).ok())
+            .collect()
+    }
+
+    /// Asserts that the response sets the cookie named `name` and returns it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the cookie isn't set or is removed.
+    #[track_caller]
+    pub fn assert_cookie_set(&self, name: &str) -> Cookie<'static> {
+        match self.set_cookies().into_iter().find(|c| c.name() == name) {
+            Some(cookie) if !is_removal(&cookie) => cookie,
+            Some(cookie) => panic!("cookie `{name}` is removed: {cookie}"),
+            None => panic!("cookie `{name}` isn't set"),
+        }
+    }
+
+    /// Asserts that the response removes the cookie named `name`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the cookie isn't removed.
+    #[track_caller]
+    pub fn assert_cookie_removed(&self, name: &str) {
+        match self.set_cookies().into_iter().find(|c| c.name() == name) {
+            Some(cookie) if is_removal(&cookie) => (),
+            Some(cookie) => panic!("cookie `{name}` isn't removed: {cookie}"),
+            None => panic!("cookie `{name}` isn't set"),
+        }
+    }
+
+    /// Returns the response.
+    pub fn into_inner(self) -> Response<B> {
+        self.response
+    }
+}
+
+impl<B> Deref for TestResponse<B> {
+    type Target = Response<B>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.response
+    }
+}
+
+impl<B> DerefMut for TestResponse<B> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.response
+    }
+}
+
+#[cfg(all(test, feature = "axum-core"))]
+mod tests {
+    use super::*;
+    use crate::{CookieManagerLayer, Cookies};
+    use axum::{routing::get, Router};
+
+    fn app() -> Router {
+        Router::new()
+            .route(
+                "/echo",
+                get(|cookies: Cookies| async move {
+                    let mut items: Vec<_> = cookies.list().iter().map(|c| c.to_string()).collect();
+                    items.sort();
+                    items.join(", ")
+                }),
+            )
+            .route(
+                "/set",
+                get(|cookies: Cookies| async move {
+                    cookies.add(Cookie::new("foo", "1"));
+                }),
+            )
+            .route(
+                "/remove",
+                get(|cookies: Cookies| async move {
+                    cookies.remove(Cookie::new("foo", ""));
+                }),
+            )
+            .layer(CookieManagerLayer::new())
+    }
+
+    #[tokio::test]
+    async fn round_trip() {
+        let mut client = TestClient::new(app());
+        let res = client.get("/set").await.unwrap();
+        assert_eq!(res.assert_cookie_set("foo").value(), "1");
+
+        client.add_cookie(Cookie::new("bar", "2"));
+        let res = client.get("/echo").await.unwrap();
+        let body = axum::body::to_bytes(res.into_inner().into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(body, "bar=2, foo=1");
+
+        let res = client.get("/remove").await.unwrap();
+        res.assert_cookie_removed("foo");
+        assert!(client.get_cookie("foo").is_none());
+    }
+
+    #[tokio::test]
+    #[should_panic(expected = "cookie `bar` isn't set")]
+    async fn assert_cookie_set() {
+        let mut client = TestClient::new(app());
+        client.get("/set").await.unwrap().assert_cookie_set("bar");
+    }
+
+    #[cfg(feature = "signed")]
+    #[tokio::test]
+    async fn signed() {
+        let key = Key::generate();
+        let app = Router::new()
+            .route(
+                "/",
+                get(|cookies: Cookies| async move {
+                    let signed = cookies.signed_owned().unwrap();
+                    let value = signed.get("foo").unwrap().value().to_owned();
+                    signed.add(Cookie::new("bar", value));
+                }),
+            )
+            .layer(CookieManagerLayer::new().with_key(key.clone()));
+        let mut client = TestClient::new(app);
+        client.add_signed_cookie(Cookie::new("foo", "1"), &key);
+        client.get("/").await.unwrap();
+        assert_eq!(client.get_signed_cookie("bar", &key).unwrap().value(), "1");
+        assert!(client.get_signed_cookie("bar", &Key::generate()).is_none());
+    }
+}
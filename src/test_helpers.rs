@@ -0,0 +1,33 @@
+//! Helpers shared by the unit tests.
+
+// Not every helper is used with every set of features
+#![allow(dead_code)]
+
+use axum::{body::Body, Router};
+use http::{header, Request, Response};
+use http_body_util::BodyExt;
+use tower::ServiceExt;
+
+/// Sends a `GET` request to the `uri`, with the `Cookie` header if `cookie` is given.
+pub(crate) async fn call(app: &Router, uri: &str, cookie: Option<&str>) -> Response<Body> {
+    let mut req = Request::builder().uri(uri);
+    if let Some(cookie) = cookie {
+        req = req.header(header::COOKIE, cookie);
+    }
+    app.clone()
+        .oneshot(req.body(Body::empty()).unwrap())
+        .await
+        .unwrap()
+}
+
+/// Returns the `name=value` pair of the first `Set-Cookie` header of the response.
+pub(crate) fn set_cookie(res: &Response<Body>) -> Option<String> {
+    let value = res.headers().get(header::SET_COOKIE)?.to_str().unwrap();
+    Some(value.split(';').next().unwrap().to_owned())
+}
+
+/// Collects the body of the response into a string.
+pub(crate) async fn body_string(res: Response<Body>) -> String {
+    let bytes = res.into_body().collect().await.unwrap().to_bytes();
+    String::from_utf8_lossy(&bytes).into()
+}
@@ -0,0 +1,600 @@
+//! Typed cookies serialized with [`serde`].
+//!
+//! Values are converted to and from cookie values by a [`Codec`]. The methods without a codec
+//! parameter, e.g. [`Cookies::get_typed`], use [`Json`].
+//!
+//! # Example:
+//! ```
+//! use serde::{Deserialize, Serialize};
+//! use tower_cookies::{typed::TypedCookieError, Cookies};
+//!
+//! #[derive(Debug, PartialEq, Deserialize, Serialize)]
+//! struct Prefs {
+//!     theme: String,
+//! }
+//!
+//! let cookies = Cookies::default();
+//! assert!(matches!(
+//!     cookies.get_typed::<Prefs>("prefs"),
+//!     Err(TypedCookieError::Missing)
+//! ));
+//!
+//! let prefs = Prefs {
+//!     theme: "dark".into(),
+//! };
+//! cookies.add_typed("prefs", &prefs).unwrap();
+//! assert_eq!(cookies.get_typed::<Prefs>("prefs").unwrap(), prefs);
+//! ```
+//!
+//! With the `derive` feature, a cookie can be declared once with its name and attributes, see
+//! [`TypedCookie`].
+
+use crate::Cookies;
+#[cfg(feature = "private")]
+use crate::PrivateCookies;
+#[cfg(feature = "signed")]
+use crate::SignedCookies;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use cookie::Cookie;
+use serde::{de::DeserializeOwned, Serialize};
+use std::{borrow::Cow, error::Error, fmt};
+
+type BoxError = Box<dyn Error + Send + Sync>;
+
+/// Converts values to and from cookie values.
+pub trait Codec {
+    /// Encodes the `value` into a string valid as a cookie value.
+    fn encode<T: Serialize + ?Sized>(value: &T) -> Result<String, TypedCookieError>;
+
+    /// Decodes a value from the cookie `value`.
+    fn decode<T: DeserializeOwned>(value: &str) -> Result<T, TypedCookieError>;
+}
+
+/// JSON codec.
+///
+/// The JSON is encoded with unpadded base64url, because quotes, commas and spaces aren't allowed
+/// in cookie values.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Json;
+
+impl Codec for Json {
+    fn encode<T: Serialize + ?Sized>(value: &T) -> Result<String, TypedCookieError> {
+        let json = serde_json::to_vec(value).map_err(TypedCookieError::serialize)?;
+        Ok(URL_SAFE_NO_PAD.encode(json))
+    }
+
+    fn decode<T: DeserializeOwned>(value: &str) -> Result<T, TypedCookieError> {
+        let json = URL_SAFE_NO_PAD
+            .decode(value)
+            .map_err(TypedCookieError::malformed)?;
+        serde_json::from_slice(&json).map_err(TypedCookieError::malformed)
+    }
+}
+
+/// [`postcard`] codec, producing more compact values than [`Json`].
+///
+/// The binary data is encoded with unpadded base64url.
+#[cfg(feature = "postcard")]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Postcard;
+
+#[cfg(feature = "postcard")]
+impl Codec for Postcard {
+    fn encode<T: Serialize + ?Sized>(value: &T) -> Result<String, TypedCookieError> {
+        let bytes = postcard::to_allocvec(value).map_err(TypedCookieError::serialize)?;
+        Ok(URL_SAFE_NO_PAD.encode(bytes))
+    }
+
+    fn decode<T: DeserializeOwned>(value: &str) -> Result<T, TypedCookieError> {
+        let bytes = URL_SAFE_NO_PAD
+            .decode(value)
+            .map_err(TypedCookieError::malformed)?;
+        postcard::from_bytes(&bytes).map_err(TypedCookieError::malformed)
+    }
+}
+
+/// An error of reading or writing a typed cookie.
+#[derive(Debug)]
+pub enum TypedCookieError {
+    /// The cookie doesn't exist, or it fails to verify or decrypt in a signed or private jar.
+    Missing,
+    /// The cookie value can't be decoded.
+    Malformed(BoxError),
+    /// The value can't be encoded.
+    Serialize(BoxError),
+    /// The cookie is signed or private, but [`CookieManagerLayer`](crate::CookieManagerLayer) has
+    /// no key.
+    MissingKey,
+    /// The cookie is declared for another jar than the given [`SignedCookies`] or
+    /// [`PrivateCookies`].
+    JarMismatch,
+}
+
+impl TypedCookieError {
+    fn malformed(err: impl Into<BoxError>) -> Self {
+        Self::Malformed(err.into())
+    }
+
+    fn serialize(err: impl Into<BoxError>) -> Self {
+        Self::Serialize(err.into())
+    }
+}
+
+impl fmt::Display for TypedCookieError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Missing => f.write_str("cookie is missing"),
+            Self::Malformed(err) => write!(f, "malformed cookie value: {err}"),
+            Self::Serialize(err) => write!(f, "can't serialize cookie value: {err}"),
+            Self::MissingKey => f.write_str("cookie manager layer has no key"),
+            Self::JarMismatch => f.write_str("cookie is declared for another jar"),
+        }
+    }
+}
+
+impl Error for TypedCookieError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Missing | Self::MissingKey | Self::JarMismatch => None,
+            Self::Malformed(err) | Self::Serialize(err) => Some(&**err),
+        }
+    }
+}
+
+fn decode<C: Codec, T: DeserializeOwned>(
+    cookie: Option<Cookie<'_>>,
+) -> Result<T, TypedCookieError> {
+    C::decode(cookie.ok_or(TypedCookieError::Missing)?.value())
+}
+
+fn encode<C: Codec, T: Serialize + ?Sized>(
+    name: impl Into<Cow<'static, str>>,
+    value: &T,
+) -> Result<Cookie<'static>, TypedCookieError> {
+    Ok(Cookie::new(name, C::encode(value)?))
+}
+
+macro_rules! impl_typed {
+    ($jar:ty) => {
+        impl $jar {
+            /// Returns the value of the cookie with the given name decoded from JSON.
+            pub fn get_typed<T: DeserializeOwned>(
+                &self,
+                name: &str,
+            ) -> Result<T, TypedCookieError> {
+                self.get_typed_with::<Json, T>(name)
+            }
+
+            /// Returns the value of the cookie with the given name decoded by the codec `C`.
+            pub fn get_typed_with<C: Codec, T: DeserializeOwned>(
+                &self,
+                name: &str,
+            ) -> Result<T, TypedCookieError> {
+                decode::<C, T>(self.get(name))
+            }
+
+            /// Adds a cookie with the given name and the `value` encoded to JSON.
+            ///
+            /// The cookie has no attributes besides the defaults configured on
+            /// [`CookieManagerLayer`](crate::CookieManagerLayer). To set them, create the cookie
+            /// manually with a value encoded by [`Codec::encode`].
+            pub fn add_typed<T: Serialize + ?Sized>(
+                &self,
+                name: impl Into<Cow<'static, str>>,
+                value: &T,
+            ) -> Result<(), TypedCookieError> {
+                self.add_typed_with::<Json, T>(name, value)
+            }
+
+            /// Adds a cookie with the given name and the `value` encoded by the codec `C`.
+            pub fn add_typed_with<C: Codec, T: Serialize + ?Sized>(
+                &self,
+                name: impl Into<Cow<'static, str>>,
+                value: &T,
+            ) -> Result<(), TypedCookieError> {
+                self.add(encode::<C, T>(name, value)?);
+                Ok(())
+            }
+        }
+    };
+}
+
+impl_typed!(Cookies);
+
+#[cfg(feature = "signed")]
+impl_typed!(SignedCookies<'_>);
+
+#[cfg(feature = "private")]
+impl_typed!(PrivateCookies<'_>);
+
+/// The jar a [`TypedCookie`] is stored in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum JarKind {
+    /// [`Cookies`] itself.
+    Plain,
+    /// [`SignedCookies`] using the keys of [`CookieManagerLayer`](crate::CookieManagerLayer).
+    #[cfg(feature = "signed")]
+    Signed,
+    /// [`PrivateCookies`] using the keys of [`CookieManagerLayer`](crate::CookieManagerLayer).
+    #[cfg(feature = "private")]
+    Private,
+}
+
+/// A cookie declared as a type, with its name, jar and attributes in one place.
+///
+/// It's usually implemented with `#[derive(Cookie)]` of the `derive` feature, configured by the
+/// `#[cookie(...)]` attribute:
+///
+/// - `name = "..."`: the name of the cookie, the type name in snake case by default, e.g.
+///   `http_prefs` for `HTTPPrefs`.
+/// - `signed` or `private`: stores the cookie in [`SignedCookies`] or [`PrivateCookies`], see
+///   [`TypedJar`]. They require the `signed` or `private` feature.
+/// - `path = "..."` and `domain = "..."`.
+/// - `max_age = "30d"`: a number of seconds, or a number with one of the `s`, `m`, `h`, `d` or
+///   `w` units.
+/// - `same_site = "lax"`: one of `strict`, `lax` or `none`.
+/// - `secure` and `http_only`, optionally with a value, e.g. `secure = false`.
+/// - `codec = Postcard`: a [`Codec`], [`Json`] by default.
+///
+/// The unset attributes get the defaults configured on
+/// [`CookieManagerLayer`](crate::CookieManagerLayer).
+///
+/// # Example:
+/// ```
+/// # #[cfg(feature = "derive")] {
+/// use serde::{Deserialize, Serialize};
+/// use tower_cookies::{typed::TypedCookie, Cookie, Cookies};
+///
+/// #[derive(Debug, PartialEq, Deserialize, Serialize, Cookie)]
+/// #[cookie(name = "prefs", max_age = "30d", same_site = "lax")]
+/// struct Prefs {
+///     theme: String,
+/// }
+///
+/// let cookies = Cookies::default();
+/// let prefs = Prefs {
+///     theme: "dark".into(),
+/// };
+/// prefs.set(&cookies).unwrap();
+/// assert_eq!(Prefs::get(&cookies).unwrap(), prefs);
+///
+/// let cookie = cookies.get("prefs").unwrap();
+/// assert_eq!(cookie.max_age(), Some(cookie::time::Duration::days(30)));
+///
+/// Prefs::remove(&cookies);
+/// assert!(Prefs::get(&cookies).is_err());
+/// # }
+/// ```
+pub trait TypedCookie: Serialize + DeserializeOwned {
+    /// The codec of the value.
+    type Codec: Codec;
+
+    /// The name of the cookie.
+    const NAME: &'static str;
+
+    /// The jar the cookie is stored in.
+    const JAR: JarKind;
+
+    /// Returns the cookie with the encoded `value` and the declared attributes.
+    fn cookie(value: String) -> Cookie<'static>;
+
+    /// Returns the value of the cookie from the `jar`.
+    fn get<J: TypedJar + ?Sized>(jar: &J) -> Result<Self, TypedCookieError> {
+        decode::<Self::Codec, Self>(jar.typed_get(Self::JAR, Self::NAME)?)
+    }
+
+    /// Adds the cookie with the value of `self` to the `jar`.
+    fn set<J: TypedJar + ?Sized>(&self, jar: &J) -> Result<(), TypedCookieError> {
+        jar.typed_add(Self::JAR, Self::cookie(Self::Codec::encode(self)?))
+    }
+
+    /// Removes the cookie, using the declared `path` and `domain` for the removal cookie.
+    fn remove<J: TypedJar + ?Sized>(jar: &J) {
+        jar.typed_remove(Self::cookie(String::new()));
+    }
+}
+
+/// A jar storing [`TypedCookie`]s: [`Cookies`], [`SignedCookies`] or [`PrivateCookies`].
+///
+/// With [`Cookies`], the signed and private cookies use the keys set by
+/// [`CookieManagerLayer::with_key`](crate::CookieManagerLayer::with_key). A [`SignedCookies`] or
+/// [`PrivateCookies`] jar, e.g. one using a borrowed key or the key of the router state, only
+/// accepts the cookies declared for it, and returns [`TypedCookieError::JarMismatch`] otherwise.
+///
+/// # Example:
+/// ```
+/// # #[cfg(all(feature = "derive", feature = "signed"))] {
+/// use serde::{Deserialize, Serialize};
+/// use tower_cookies::{typed::TypedCookie, Cookie, Cookies, Key};
+///
+/// #[derive(Debug, PartialEq, Deserialize, Serialize, Cookie)]
+/// #[cookie(signed)]
+/// struct UserId(u64);
+///
+/// let key = Key::generate();
+/// let cookies = Cookies::default();
+/// UserId(1).set(&cookies.signed(&key)).unwrap();
+/// assert_eq!(UserId::get(&cookies.signed(&key)).unwrap(), UserId(1));
+/// # }
+/// ```
+pub trait TypedJar: sealed::Sealed {}
+
+mod sealed {
+    use super::{JarKind, TypedCookieError};
+    use cookie::Cookie;
+
+    pub trait Sealed {
+        fn typed_get(
+            &self,
+            kind: JarKind,
+            name: &str,
+        ) -> Result<Option<Cookie<'static>>, TypedCookieError>;
+
+        fn typed_add(&self, kind: JarKind, cookie: Cookie<'static>)
+            -> Result<(), TypedCookieError>;
+
+        fn typed_remove(&self, cookie: Cookie<'static>);
+    }
+}
+
+impl TypedJar for Cookies {}
+
+impl sealed::Sealed for Cookies {
+    fn typed_get(
+        &self,
+        kind: JarKind,
+        name: &str,
+    ) -> Result<Option<Cookie<'static>>, TypedCookieError> {
+        Ok(match kind {
+            JarKind::Plain => self.get(name).map(Cookie::into_owned),
+            #[cfg(feature = "signed")]
+            JarKind::Signed => signed(self)?.get(name),
+            #[cfg(feature = "private")]
+            JarKind::Private => private(self)?.get(name),
+        })
+    }
+
+    fn typed_add(&self, kind: JarKind, cookie: Cookie<'static>) -> Result<(), TypedCookieError> {
+        match kind {
+            JarKind::Plain => self.add(cookie),
+            #[cfg(feature = "signed")]
+            JarKind::Signed => signed(self)?.add(cookie),
+            #[cfg(feature = "private")]
+            JarKind::Private => private(self)?.add(cookie),
+        }
+        Ok(())
+    }
+
+    fn typed_remove(&self, cookie: Cookie<'static>) {
+        self.remove(cookie);
+    }
+}
+
+#[cfg(any(feature = "signed", feature = "private"))]
+macro_rules! impl_typed_jar {
+    ($jar:ty, $kind:ident) => {
+        impl TypedJar for $jar {}
+
+        impl sealed::Sealed for $jar {
+            fn typed_get(
+                &self,
+                kind: JarKind,
+                name: &str,
+            ) -> Result<Option<Cookie<'static>>, TypedCookieError> {
+                match kind {
+                    JarKind::$kind => Ok(self.get(name)),
+                    _ => Err(TypedCookieError::JarMismatch),
+                }
+            }
+
+            fn typed_add(
+                &self,
+                kind: JarKind,
+                cookie: Cookie<'static>,
+            ) -> Result<(), TypedCookieError> {
+                match kind {
+                    JarKind::$kind => Ok(self.add(cookie)),
+                    _ => Err(TypedCookieError::JarMismatch),
+                }
+            }
+
+            fn typed_remove(&self, cookie: Cookie<'static>) {
+                self.remove(cookie);
+            }
+        }
+    };
+}
+
+#[cfg(feature = "signed")]
+impl_typed_jar!(SignedCookies<'_>, Signed);
+
+#[cfg(feature = "private")]
+impl_typed_jar!(PrivateCookies<'_>, Private);
+
+#[cfg(feature = "signed")]
+fn signed(cookies: &Cookies) -> Result<SignedCookies<'static>, TypedCookieError> {
+    cookies.signed_owned().ok_or(TypedCookieError::MissingKey)
+}
+
+#[cfg(feature = "private")]
+fn private(cookies: &Cookies) -> Result<PrivateCookies<'static>, TypedCookieError> {
+    cookies.private_owned().ok_or(TypedCookieError::MissingKey)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+
+    #[derive(Debug, PartialEq, Deserialize, Serialize)]
+    struct Prefs {
+        theme: String,
+        size: u8,
+    }
+
+    fn prefs() -> Prefs {
+        Prefs {
+            theme: "dark; light".into(),
+            size: 12,
+        }
+    }
+
+    #[test]
+    fn missing() {
+        let cookies = Cookies::new(vec![]);
+        assert!(matches!(
+            cookies.get_typed::<Prefs>("prefs"),
+            Err(TypedCookieError::Missing)
+        ));
+    }
+
+    #[test]
+    fn malformed() {
+        let cookies = Cookies::new(vec![]);
+        cookies.add(Cookie::new("prefs", "1"));
+        assert!(matches!(
+            cookies.get_typed::<Prefs>("prefs"),
+            Err(TypedCookieError::Malformed(_))
+        ));
+    }
+
+    #[test]
+    fn json() {
+        let cookies = Cookies::new(vec![]);
+        cookies.add_typed("prefs", &prefs()).unwrap();
+        assert!(!cookies.get("prefs").unwrap().value().contains(';'));
+        assert_eq!(cookies.get_typed::<Prefs>("prefs").unwrap(), prefs());
+    }
+
+    #[cfg(feature = "postcard")]
+    #[test]
+    fn postcard() {
+        let cookies = Cookies::new(vec![]);
+        cookies
+            .add_typed_with::<Postcard, _>("prefs", &prefs())
+            .unwrap();
+        assert_eq!(
+            cookies.get_typed_with::<Postcard, Prefs>("prefs").unwrap(),
+            prefs()
+        );
+    }
+
+    #[cfg(feature = "signed")]
+    #[test]
+    fn signed() {
+        let key = cookie::Key::generate();
+        let cookies = Cookies::new(vec![]);
+        cookies.signed(&key).add_typed("prefs", &prefs()).unwrap();
+        assert_eq!(
+            cookies.signed(&key).get_typed::<Prefs>("prefs").unwrap(),
+            prefs()
+        );
+        assert!(matches!(
+            cookies
+                .signed(&cookie::Key::generate())
+                .get_typed::<Prefs>("prefs"),
+            Err(TypedCookieError::Missing)
+        ));
+    }
+
+    #[cfg(feature = "private")]
+    #[test]
+    fn private() {
+        let key = cookie::Key::generate();
+        let cookies = Cookies::new(vec![]);
+        cookies.private(&key).add_typed("prefs", &prefs()).unwrap();
+        assert_eq!(
+            cookies.private(&key).get_typed::<Prefs>("prefs").unwrap(),
+            prefs()
+        );
+    }
+
+    #[cfg(feature = "derive")]
+    #[test]
+    fn derive() {
+        #[derive(Debug, PartialEq, Deserialize, Serialize, crate::Cookie)]
+        #[cookie(
+            path = "/app",
+            max_age = "2h",
+            same_site = "strict",
+            secure,
+            http_only = false
+        )]
+        struct UserPrefs {
+            theme: String,
+        }
+
+        assert_eq!(UserPrefs::NAME, "user_prefs");
+        assert_eq!(UserPrefs::JAR, JarKind::Plain);
+        let cookies = Cookies::new(vec![]);
+        let prefs = UserPrefs {
+            theme: "dark".into(),
+        };
+        prefs.set(&cookies).unwrap();
+        let cookie = cookies.get("user_prefs").unwrap();
+        assert_eq!(cookie.path(), Some("/app"));
+        assert_eq!(cookie.max_age(), Some(cookie::time::Duration::hours(2)));
+        assert_eq!(cookie.same_site(), Some(cookie::SameSite::Strict));
+        assert_eq!(cookie.secure(), Some(true));
+        assert_eq!(cookie.http_only(), Some(false));
+        assert_eq!(UserPrefs::get(&cookies).unwrap(), prefs);
+
+        UserPrefs::remove(&cookies);
+        assert!(matches!(
+            UserPrefs::get(&cookies),
+            Err(TypedCookieError::Missing)
+        ));
+    }
+
+    #[cfg(all(feature = "derive", feature = "signed"))]
+    #[test]
+    fn derive_signed() {
+        use crate::{service::Config, KeyRing};
+        use std::sync::Arc;
+
+        #[derive(Debug, PartialEq, Deserialize, Serialize, crate::Cookie)]
+        #[cookie(name = "prefs", signed)]
+        struct Prefs {
+            size: u8,
+        }
+
+        let prefs = Prefs { size: 12 };
+        let cookies = Cookies::new(vec![]);
+        assert!(matches!(
+            prefs.set(&cookies),
+            Err(TypedCookieError::MissingKey)
+        ));
+
+        let key = cookie::Key::generate();
+        let cookies = cookies.with_config(Arc::new(Config {
+            keys: Some(Arc::new(KeyRing::new(key.clone()))),
+            ..Default::default()
+        }));
+        prefs.set(&cookies).unwrap();
+        assert_eq!(Prefs::get(&cookies).unwrap(), prefs);
+        assert_eq!(
+            cookies.signed(&key).get_typed::<Prefs>("prefs").unwrap(),
+            prefs
+        );
+
+        // A jar using a borrowed key
+        let cookies = Cookies::new(vec![]);
+        let key = cookie::Key::generate();
+        Prefs { size: 1 }.set(&cookies.signed(&key)).unwrap();
+        assert_eq!(
+            Prefs::get(&cookies.signed(&key)).unwrap(),
+            Prefs { size: 1 }
+        );
+        Prefs::remove(&cookies.signed(&key));
+        assert!(matches!(
+            Prefs::get(&cookies.signed(&key)),
+            Err(TypedCookieError::Missing)
+        ));
+        #[cfg(feature = "private")]
+        assert!(matches!(
+            Prefs::get(&cookies.private(&key)),
+            Err(TypedCookieError::JarMismatch)
+        ));
+    }
+}
@@ -0,0 +1,143 @@
+use cookie::Cookie;
+use http::HeaderValue;
+use std::{error::Error, fmt};
+
+/// A cookie which can't be sent in a `Set-Cookie` header as is.
+///
+/// Returned by [`Cookies::try_add`](crate::Cookies::try_add) and passed to the hook set by
+/// [`CookieManagerLayer::on_invalid_cookie`](crate::CookieManagerLayer::on_invalid_cookie).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InvalidCookie {
+    name: String,
+    reason: InvalidCookieReason,
+}
+
+/// The reason a cookie can't be sent.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum InvalidCookieReason {
+    /// The name is empty.
+    EmptyName,
+    /// The name contains whitespace, control characters, `=` or `;`.
+    InvalidName,
+    /// The value contains control characters or `;`.
+    InvalidValue,
+    /// An attribute, e.g. `Path` or `Domain`, contains characters not allowed in a header.
+    InvalidAttribute,
+    /// The `Domain` is a public suffix, e.g. `co.uk`, so the cookie is sent without it, as a
+    /// host-only cookie.
+    #[cfg(feature = "psl")]
+    PublicSuffixDomain,
+}
+
+impl InvalidCookie {
+    /// Returns the name of the cookie.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the reason the cookie can't be sent.
+    pub fn reason(&self) -> InvalidCookieReason {
+        self.reason
+    }
+}
+
+impl fmt::Display for InvalidCookie {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let reason = match self.reason {
+            InvalidCookieReason::EmptyName => "the name is empty",
+            InvalidCookieReason::InvalidName => "the name contains characters not allowed in it",
+            InvalidCookieReason::InvalidValue => {
+                "the value contains characters not allowed in it, percent-encode the value"
+            }
+            InvalidCookieReason::InvalidAttribute => {
+                "an attribute contains characters not allowed in a header"
+            }
+            #[cfg(feature = "psl")]
+            InvalidCookieReason::PublicSuffixDomain => {
+                "the domain is a public suffix, the cookie is sent host-only"
+            }
+        };
+        write!(f, "invalid cookie `{}`: {reason}", self.name.escape_debug())
+    }
+}
+
+impl Error for InvalidCookie {}
+
+/// Checks that the `cookie` can be sent in a `Set-Cookie` header and parsed back by the client.
+pub(crate) fn validate(cookie: &Cookie<'_>) -> Result<(), InvalidCookie> {
+    let error = |reason| {
+        Err(InvalidCookie {
+            name: cookie.name().to_owned(),
+            reason,
+        })
+    };
+    let name = cookie.name();
+    if name.is_empty() {
+        return error(InvalidCookieReason::EmptyName);
+    }
+    if name
+        .chars()
+        .any(|c| c.is_whitespace() || c.is_control() || c == '=' || c == ';')
+    {
+        return error(InvalidCookieReason::InvalidName);
+    }
+    if cookie.value().chars().any(|c| c.is_control() || c == ';') {
+        return error(InvalidCookieReason::InvalidValue);
+    }
+    if HeaderValue::from_str(&cookie.to_string()).is_err() {
+        return error(InvalidCookieReason::InvalidAttribute);
+    }
+    Ok(())
+}
+
+/// Returns the error of the `cookie` which isn't a valid header value.
+pub(crate) fn invalid_header(cookie: &Cookie<'_>) -> InvalidCookie {
+    validate(cookie).err().unwrap_or_else(|| InvalidCookie {
+        name: cookie.name().to_owned(),
+        reason: InvalidCookieReason::InvalidAttribute,
+    })
+}
+
+/// Returns the error of the `cookie` with a public suffix `Domain`.
+#[cfg(feature = "psl")]
+pub(crate) fn public_suffix_domain(cookie: &Cookie<'_>) -> InvalidCookie {
+    InvalidCookie {
+        name: cookie.name().to_owned(),
+        reason: InvalidCookieReason::PublicSuffixDomain,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reason(cookie: Cookie<'_>) -> Option<InvalidCookieReason> {
+        validate(&cookie).err().map(|e| e.reason())
+    }
+
+    #[test]
+    fn reasons() {
+        assert_eq!(reason(Cookie::new("a", "b é")), None);
+        assert_eq!(
+            reason(Cookie::new("", "1")),
+            Some(InvalidCookieReason::EmptyName)
+        );
+        assert_eq!(
+            reason(Cookie::new("a b", "1")),
+            Some(InvalidCookieReason::InvalidName)
+        );
+        assert_eq!(
+            reason(Cookie::new("a", "1\n")),
+            Some(InvalidCookieReason::InvalidValue)
+        );
+        assert_eq!(
+            reason(Cookie::new("a", "1;2")),
+            Some(InvalidCookieReason::InvalidValue)
+        );
+        assert_eq!(
+            reason(Cookie::build(("a", "1")).path("/\r").build()),
+            Some(InvalidCookieReason::InvalidAttribute)
+        );
+    }
+}
@@ -0,0 +1,241 @@
+//! Derive macro for [`tower-cookies`](https://docs.rs/tower-cookies).
+//!
+//! Use it through the `derive` feature of `tower-cookies`, which re-exports the macro as
+//! `tower_cookies::Cookie`.
+
+#![warn(clippy::all, missing_docs, nonstandard_style, future_incompatible)]
+#![forbid(unsafe_code)]
+
+use proc_macro::TokenStream;
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::quote;
+use syn::{parse_macro_input, Attribute, DeriveInput, Lit, LitBool, LitStr, Path, Token};
+
+/// Implements `tower_cookies::typed::TypedCookie` for a serializable type.
+///
+/// See the documentation of `TypedCookie` for the supported `#[cookie(...)]` attributes.
+#[proc_macro_derive(Cookie, attributes(cookie))]
+pub fn derive_cookie(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+enum Jar {
+    Plain,
+    Signed,
+    Private,
+}
+
+#[derive(Default)]
+struct Attrs {
+    name: Option<LitStr>,
+    jar: Option<Jar>,
+    path: Option<LitStr>,
+    domain: Option<LitStr>,
+    /// In seconds
+    max_age: Option<i64>,
+    same_site: Option<TokenStream2>,
+    secure: Option<bool>,
+    http_only: Option<bool>,
+    codec: Option<Path>,
+}
+
+fn expand(input: DeriveInput) -> syn::Result<TokenStream2> {
+    let attrs = parse_attrs(&input.attrs)?;
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let name = attrs
+        .name
+        .unwrap_or_else(|| LitStr::new(&snake_case(&ident.to_string()), Span::call_site()));
+    if name.value().is_empty() {
+        return Err(syn::Error::new(name.span(), "cookie name can't be empty"));
+    }
+    let jar = match attrs.jar.unwrap_or(Jar::Plain) {
+        Jar::Plain => quote!(Plain),
+        Jar::Signed => quote!(Signed),
+        Jar::Private => quote!(Private),
+    };
+    let codec = match attrs.codec {
+        Some(codec) => quote!(#codec),
+        None => quote!(::tower_cookies::typed::Json),
+    };
+
+    let mut setters = Vec::new();
+    if let Some(path) = attrs.path {
+        setters.push(quote!(cookie.set_path(#path);));
+    }
+    if let Some(domain) = attrs.domain {
+        setters.push(quote!(cookie.set_domain(#domain);));
+    }
+    if let Some(seconds) = attrs.max_age {
+        setters.push(quote! {
+            cookie.set_max_age(::tower_cookies::cookie::time::Duration::seconds(#seconds));
+        });
+    }
+    if let Some(same_site) = attrs.same_site {
+        setters.push(quote! {
+            cookie.set_same_site(::tower_cookies::cookie::SameSite::#same_site);
+        });
+    }
+    if let Some(secure) = attrs.secure {
+        setters.push(quote!(cookie.set_secure(#secure);));
+    }
+    if let Some(http_only) = attrs.http_only {
+        setters.push(quote!(cookie.set_http_only(#http_only);));
+    }
+
+    Ok(quote! {
+        impl #impl_generics ::tower_cookies::typed::TypedCookie for #ident #ty_generics
+        #where_clause
+        {
+            type Codec = #codec;
+
+            const NAME: &'static str = #name;
+
+            const JAR: ::tower_cookies::typed::JarKind = ::tower_cookies::typed::JarKind::#jar;
+
+            fn cookie(value: ::std::string::String) -> ::tower_cookies::Cookie<'static> {
+                #[allow(unused_mut)]
+                let mut cookie = ::tower_cookies::Cookie::new(Self::NAME, value);
+                #(#setters)*
+                cookie
+            }
+        }
+    })
+}
+
+fn parse_attrs(attrs: &[Attribute]) -> syn::Result<Attrs> {
+    let mut parsed = Attrs::default();
+    for attr in attrs.iter().filter(|attr| attr.path().is_ident("cookie")) {
+        attr.parse_nested_meta(|meta| {
+            let key = &meta.path;
+            if key.is_ident("name") {
+                parsed.name = Some(meta.value()?.parse()?);
+            } else if key.is_ident("signed") || key.is_ident("private") {
+                if parsed.jar.is_some() {
+                    return Err(meta.error("only one of `signed` and `private` is allowed"));
+                }
+                parsed.jar = Some(if key.is_ident("signed") {
+                    Jar::Signed
+                } else {
+                    Jar::Private
+                });
+            } else if key.is_ident("path") {
+                parsed.path = Some(meta.value()?.parse()?);
+            } else if key.is_ident("domain") {
+                parsed.domain = Some(meta.value()?.parse()?);
+            } else if key.is_ident("max_age") {
+                let seconds = match meta.value()?.parse()? {
+                    Lit::Int(lit) => lit.base10_parse()?,
+                    Lit::Str(lit) => parse_duration(&lit.value()).ok_or_else(|| {
+                        syn::Error::new(
+                            lit.span(),
+                            "expected a duration like `3600`, `30m`, `12h`, `30d` or `2w`",
+                        )
+                    })?,
+                    lit => return Err(syn::Error::new(lit.span(), "expected a duration")),
+                };
+                parsed.max_age = Some(seconds);
+            } else if key.is_ident("same_site") {
+                let lit: LitStr = meta.value()?.parse()?;
+                parsed.same_site = Some(match lit.value().to_ascii_lowercase().as_str() {
+                    "strict" => quote!(Strict),
+                    "lax" => quote!(Lax),
+                    "none" => quote!(None),
+                    _ => {
+                        return Err(syn::Error::new(
+                            lit.span(),
+                            "expected `strict`, `lax` or `none`",
+                        ))
+                    }
+                });
+            } else if key.is_ident("secure") {
+                parsed.secure = Some(parse_flag(&meta)?);
+            } else if key.is_ident("http_only") {
+                parsed.http_only = Some(parse_flag(&meta)?);
+            } else if key.is_ident("codec") {
+                parsed.codec = Some(meta.value()?.parse()?);
+            } else {
+                return Err(meta.error("unknown cookie attribute"));
+            }
+            Ok(())
+        })?;
+    }
+    Ok(parsed)
+}
+
+/// Parses either `flag` or `flag = bool`.
+fn parse_flag(meta: &syn::meta::ParseNestedMeta<'_>) -> syn::Result<bool> {
+    if meta.input.peek(Token![=]) {
+        Ok(meta.value()?.parse::<LitBool>()?.value)
+    } else {
+        Ok(true)
+    }
+}
+
+/// Parses a number of seconds with an optional unit suffix: `s`, `m`, `h`, `d` or `w`.
+fn parse_duration(s: &str) -> Option<i64> {
+    let s = s.trim();
+    let (number, unit) = match s.find(|c: char| !c.is_ascii_digit()) {
+        Some(i) => s.split_at(i),
+        None => (s, "s"),
+    };
+    let multiplier = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        "w" => 7 * 24 * 60 * 60,
+        _ => return None,
+    };
+    number.parse::<i64>().ok()?.checked_mul(multiplier)
+}
+
+/// Converts a type name, e.g. `UserPrefs`, to a cookie name, e.g. `user_prefs`. A run of
+/// capitals is a single word, e.g. `HTTPPrefs` becomes `http_prefs`.
+fn snake_case(name: &str) -> String {
+    let chars: Vec<char> = name.chars().collect();
+    let mut out = String::with_capacity(name.len() + 4);
+    for (i, &c) in chars.iter().enumerate() {
+        if c.is_uppercase() && i > 0 {
+            let prev = chars[i - 1];
+            let next_lower = chars.get(i + 1).is_some_and(|c| c.is_lowercase());
+            if prev.is_lowercase() || prev.is_ascii_digit() || (prev.is_uppercase() && next_lower) {
+                out.push('_');
+            }
+        }
+        out.extend(c.to_lowercase());
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn durations() {
+        assert_eq!(parse_duration("3600"), Some(3600));
+        assert_eq!(parse_duration("45s"), Some(45));
+        assert_eq!(parse_duration("30m"), Some(1800));
+        assert_eq!(parse_duration("12h"), Some(43200));
+        assert_eq!(parse_duration("30d"), Some(2_592_000));
+        assert_eq!(parse_duration("2w"), Some(1_209_600));
+        assert_eq!(parse_duration("d"), None);
+        assert_eq!(parse_duration("1y"), None);
+        assert_eq!(parse_duration("-1s"), None);
+    }
+
+    #[test]
+    fn names() {
+        assert_eq!(snake_case("Prefs"), "prefs");
+        assert_eq!(snake_case("UserPrefs"), "user_prefs");
+        assert_eq!(snake_case("HTTPPrefs"), "http_prefs");
+        assert_eq!(snake_case("UserID"), "user_id");
+        assert_eq!(snake_case("CSRFToken2"), "csrf_token2");
+        assert_eq!(snake_case("Oauth2State"), "oauth2_state");
+    }
+}